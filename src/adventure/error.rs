@@ -1,6 +1,12 @@
 #[derive(Debug)]
 pub enum Error {
     BadReference { from: String, to: String },
+    BadCondition { page: String, visited: String },
+    UnknownTemplate { page: String, template: String },
+    MissingTranslation { locale: String, page: String },
+    UnexpectedTranslation { locale: String, page: String },
+    TranslationOrder { locale: String, page: String },
+    TranslationLinks { locale: String, page: String },
 }
 
 impl Error {
@@ -10,6 +16,48 @@ impl Error {
             to: to.to_owned(),
         }
     }
+
+    pub fn bad_condition(page: &str, visited: &str) -> Self {
+        Self::BadCondition {
+            page: page.to_owned(),
+            visited: visited.to_owned(),
+        }
+    }
+
+    pub fn unknown_template(page: &str, template: &str) -> Self {
+        Self::UnknownTemplate {
+            page: page.to_owned(),
+            template: template.to_owned(),
+        }
+    }
+
+    pub fn missing_translation(locale: &str, page: &str) -> Self {
+        Self::MissingTranslation {
+            locale: locale.to_owned(),
+            page: page.to_owned(),
+        }
+    }
+
+    pub fn unexpected_translation(locale: &str, page: &str) -> Self {
+        Self::UnexpectedTranslation {
+            locale: locale.to_owned(),
+            page: page.to_owned(),
+        }
+    }
+
+    pub fn translation_order(locale: &str, page: &str) -> Self {
+        Self::TranslationOrder {
+            locale: locale.to_owned(),
+            page: page.to_owned(),
+        }
+    }
+
+    pub fn translation_links(locale: &str, page: &str) -> Self {
+        Self::TranslationLinks {
+            locale: locale.to_owned(),
+            page: page.to_owned(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -21,6 +69,31 @@ impl std::fmt::Display for Error {
                     "The page {from:?} tries to link to page {to:?}, but it does not exist."
                 )
             }
+            Error::BadCondition { page, visited } => write!(
+                f,
+                "The page {page:?} has a condition on visiting page {visited:?}, but it does not exist."
+            ),
+            Error::UnknownTemplate { page, template } => {
+                write!(
+                    f,
+                    "The page {page:?} uses the template {template:?}, but it is not configured."
+                )
+            }
+            Error::MissingTranslation { locale, page } => {
+                write!(f, "The {locale:?} locale is missing the page {page:?}.")
+            }
+            Error::UnexpectedTranslation { locale, page } => write!(
+                f,
+                "The {locale:?} locale has the page {page:?}, which the main script does not."
+            ),
+            Error::TranslationOrder { locale, page } => write!(
+                f,
+                "The {locale:?} locale has the page {page:?} in a different position to the main script."
+            ),
+            Error::TranslationLinks { locale, page } => write!(
+                f,
+                "The page {page:?} in the {locale:?} locale has different links or conditions to the main script."
+            ),
         }
     }
 }
@@ -1,23 +1,182 @@
 pub mod error;
+mod story;
+pub mod translation;
 
-use crate::script::{self, Script};
+use crate::script::{self, Condition, Script};
 use error::Error;
 use serde::Serialize;
+use std::{borrow::Cow, collections::BTreeMap};
+pub use story::Story;
 
 /// A link with a title to an indexed page.
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct PageLink<'a> {
     pub index: usize,
     pub text: &'a str,
+
+    /// The condition on which the link is shown, if any, such as `visited(cellar)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition<'a>>,
+
+    /// Whether the link is hidden once the player has taken it.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub once: bool,
+
+    /// A flag which must be set for the link to be shown, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden_unless: Option<&'a str>,
+
+    /// Why the link can't be taken, if it's shown but disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<&'a str>,
+}
+
+impl<'a> PageLink<'a> {
+    /// Make a link to the page at the given index, without any modifiers.
+    fn new(index: usize, text: &'a str) -> Self {
+        Self {
+            index,
+            text,
+            condition: None,
+            once: false,
+            hidden_unless: None,
+            disabled: None,
+        }
+    }
+}
+
+/// A paragraph of a page, with the condition on which it's shown.
+#[derive(Clone, Debug, Serialize)]
+pub struct Paragraph<'a> {
+    pub text: &'a str,
+
+    /// The condition on which the paragraph is shown, if any, such as `first-visit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition<'a>>,
+}
+
+/// A choice which sends the player to one of several pages at random.
+#[derive(Debug, Serialize)]
+pub struct RandomChoice<'a> {
+    pub text: &'a str,
+    pub outcomes: Vec<RandomOutcome<'a>>,
+
+    /// The condition on which the choice is shown, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition<'a>>,
+}
+
+/// One of the pages a random choice may lead to, with its weight relative to the others.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct RandomOutcome<'a> {
+    pub index: usize,
+    pub identifier: &'a str,
+    pub text: &'a str,
+    pub weight: u32,
+}
+
+impl RandomChoice<'_> {
+    /// The sum of the weights of every outcome.
+    pub fn total_weight(&self) -> u64 {
+        self.outcomes
+            .iter()
+            .map(|outcome| u64::from(outcome.weight))
+            .sum()
+    }
+}
+
+/// The chapter a page is in.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Chapter<'a> {
+    pub name: &'a str,
+    pub title: &'a str,
+
+    /// The position of the chapter among the others, starting from 1.
+    pub order: usize,
 }
 
 /// A page within an adventure.
 #[derive(Debug, Serialize)]
 pub struct Page<'a> {
+    pub identifier: &'a str,
     pub title: &'a str,
     pub index: usize,
-    pub paragraphs: &'a Vec<&'a str>,
+
+    /// The chapter the page is in, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chapter: Option<Chapter<'a>>,
+
+    pub paragraphs: &'a Vec<Cow<'a, str>>,
+
+    /// The paragraphs of the page along with their conditions, for templates which show text
+    /// depending on the pages the player has visited.
+    pub text: Vec<Paragraph<'a>>,
+
     pub links: Vec<PageLink<'a>>,
+
+    /// Choices which lead to a page picked at random.
+    pub random: Vec<RandomChoice<'a>>,
+
+    pub meta: BTreeMap<&'a str, &'a str>,
+    pub template: Option<&'a str>,
+
+    /// The pages which link to this one, with their titles as text.
+    pub backlinks: Vec<PageLink<'a>>,
+
+    /// The previous page in script order, with its title as text.
+    pub prev: Option<PageLink<'a>>,
+
+    /// The next page in script order, with its title as text.
+    pub next: Option<PageLink<'a>>,
+}
+
+impl Page<'_> {
+    /// The path of the file the page is written to, relative to the output directory. Pages in a
+    /// chapter can be written to a directory named after it.
+    pub fn file_name(&self, extension: &str, chapter_directories: bool) -> String {
+        match self.chapter {
+            Some(chapter) if chapter_directories => {
+                format!("{}/{}.{extension}", chapter.name, self.index)
+            }
+            _ => format!("{}.{extension}", self.index),
+        }
+    }
+
+    /// Whether any text, link or random choice of the page has a condition, which only the runtime
+    /// script checks.
+    pub fn has_conditions(&self) -> bool {
+        self.text
+            .iter()
+            .any(|paragraph| paragraph.condition.is_some())
+            || self.links.iter().any(|link| link.condition.is_some())
+            || self.random.iter().any(|choice| choice.condition.is_some())
+    }
+
+    /// The path from the file of the page back to the output directory, such as `../`.
+    pub fn root(&self, chapter_directories: bool) -> &'static str {
+        if chapter_directories && self.chapter.is_some() {
+            "../"
+        } else {
+            ""
+        }
+    }
+}
+
+/// Everything passed to the template when rendering a single page.
+#[derive(Debug, Serialize)]
+pub struct PageContext<'a> {
+    #[serde(flatten)]
+    pub page: &'a Page<'a>,
+    pub story: &'a Story<'a>,
+
+    /// The language code of the page, from the locale being built or the story config.
+    pub lang: Option<&'a str>,
+
+    /// The path back to the output directory from the page, for linking to shared files.
+    pub root: &'a str,
+
+    /// The tag which includes the runtime script in the page, if it's enabled in the config.
+    pub runtime: Option<String>,
 }
 
 /// Intermediate representation of an adventure, passable to handlebars for rendering.
@@ -39,56 +198,416 @@ impl<'a> Adventure<'a> {
         }
 
         if errors.is_empty() {
+            Self::add_backlinks(&mut pages);
             Ok(Self { pages })
         } else {
             Err(errors)
         }
     }
 
+    /// Check that every page which selects a template selects one of the given names.
+    pub fn check_templates(&self, names: &[&str]) -> Result<(), Vec<Error>> {
+        let errors: Vec<_> = self
+            .pages
+            .iter()
+            .filter_map(|page| {
+                let template = page.template?;
+                let known = names.contains(&template);
+                (!known).then(|| Error::unknown_template(page.identifier, template))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Add a backlink to every page for each distinct page which links to it.
+    fn add_backlinks(pages: &mut [Page<'a>]) {
+        let mut backlinks = Vec::new();
+
+        for page in pages.iter() {
+            let source = PageLink::new(page.index, page.title);
+
+            for link in &page.links {
+                backlinks.push((link.index, source.clone()));
+            }
+
+            let outcomes = page.random.iter().flat_map(|choice| &choice.outcomes);
+            for outcome in outcomes {
+                backlinks.push((outcome.index, source.clone()));
+            }
+        }
+
+        for (target, source) in backlinks {
+            let backlinks = &mut pages[target - 1].backlinks;
+
+            if !backlinks.iter().any(|link| link.index == source.index) {
+                backlinks.push(source);
+            }
+        }
+    }
+
+    /// Make a link to the page at the given index, with its title as text.
+    fn make_title_link(script: &'a Script, index: usize) -> Option<PageLink<'a>> {
+        // Indices are offset by one, since they are meant to be read by humans.
+        let page = script.pages.get(index.checked_sub(1)?)?;
+
+        Some(PageLink::new(index, page.title))
+    }
+
     fn make_page(
         script: &'a Script,
         index: usize,
         page: &'a script::Page,
     ) -> Result<Page<'a>, Vec<Error>> {
-        let links = Self::make_links(script, page)?;
+        let mut errors = Vec::new();
+        let (links, random) = Self::make_links(script, page, &mut errors);
+
+        let text = page
+            .paragraphs
+            .iter()
+            .zip(&page.paragraph_conditions)
+            .map(|(text, condition)| Paragraph {
+                text: text.as_ref(),
+                condition: Self::resolve_condition(script, page, condition, &mut errors),
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Page metadata overrides the script-wide defaults.
+        let meta = script.meta.iter().chain(&page.meta).copied().collect();
 
         Ok(Page {
+            identifier: &page.identifier,
             title: page.title,
             index,
+            chapter: Self::make_chapter(script, page),
             paragraphs: &page.paragraphs,
+            text,
             links,
+            random,
+            meta,
+            template: page.template,
+            backlinks: Vec::new(),
+            prev: Self::make_title_link(script, index - 1),
+            next: Self::make_title_link(script, index + 1),
         })
     }
 
+    /// Find the chapter a page is in, along with its order.
+    fn make_chapter(script: &'a Script, page: &'a script::Page) -> Option<Chapter<'a>> {
+        let name = page.chapter?;
+        let (position, chapter) = script
+            .chapters
+            .iter()
+            .enumerate()
+            .find(|(_, chapter)| chapter.name == name)?;
+
+        Some(Chapter {
+            name,
+            title: chapter.title,
+            // Chapters are numbered from 1, like pages.
+            order: position + 1,
+        })
+    }
+
+    /// Find the pages linked to by a page, both by its links and the outcomes of its random choices.
     fn make_links(
         script: &'a Script,
         info: &'a script::Page,
-    ) -> Result<Vec<PageLink<'a>>, Vec<Error>> {
+        errors: &mut Vec<Error>,
+    ) -> (Vec<PageLink<'a>>, Vec<RandomChoice<'a>>) {
         let mut links = Vec::with_capacity(info.links.len());
-        let mut errors = Vec::new();
+        let mut random = Vec::with_capacity(info.random.len());
 
-        for (destination, text) in &info.links {
-            match Self::find_page_index(script, destination) {
-                Some(index) => links.push(PageLink { index, text }),
-                None => errors.push(Error::bad_reference(info.identifier, destination)),
+        for link in &info.links {
+            let condition = Self::resolve_condition(script, info, &link.condition, errors);
+
+            match Self::find_page_index(script, info, &link.target) {
+                Some(index) => links.push(PageLink {
+                    index,
+                    text: link.text,
+                    condition,
+                    once: link.once,
+                    hidden_unless: link.hidden_unless,
+                    disabled: link.disabled,
+                }),
+                None => errors.push(Error::bad_reference(&info.identifier, &link.target)),
             }
         }
 
-        if errors.is_empty() {
-            Ok(links)
-        } else {
-            Err(errors)
+        for choice in &info.random {
+            let mut outcomes = Vec::with_capacity(choice.outcomes.len());
+
+            for outcome in &choice.outcomes {
+                match Self::find_page_index(script, info, &outcome.target) {
+                    Some(index) => outcomes.push(RandomOutcome {
+                        index,
+                        identifier: &script.pages[index - 1].identifier,
+                        text: outcome.text,
+                        weight: outcome.weight,
+                    }),
+                    None => errors.push(Error::bad_reference(&info.identifier, &outcome.target)),
+                }
+            }
+
+            random.push(RandomChoice {
+                text: choice.text,
+                outcomes,
+                condition: Self::resolve_condition(script, info, &choice.condition, errors),
+            });
         }
+
+        (links, random)
     }
 
-    fn find_page_index(script: &'a Script, expected: &'a str) -> Option<usize> {
-        for (index, page) in script.pages.iter().enumerate() {
-            if page.identifier == expected {
-                // Indices are offset by one, since they are meant to be read by humans.
-                return Some(index + 1);
+    /// Find the page a condition of a page depends on, so the condition refers to it by its full
+    /// identifier. Conditions on pages which don't exist are reported.
+    fn resolve_condition(
+        script: &'a Script,
+        info: &'a script::Page,
+        condition: &'a Option<Condition<'a>>,
+        errors: &mut Vec<Error>,
+    ) -> Option<Condition<'a>> {
+        let condition = condition.as_ref()?;
+        let Some(page) = condition.page() else {
+            return Some(condition.clone());
+        };
+
+        match info.find(&script.pages, page) {
+            Some(position) => Some(condition.with_page(&script.pages[position].identifier)),
+            None => {
+                errors.push(Error::bad_condition(&info.identifier, page));
+                Some(condition.clone())
             }
         }
+    }
+
+    fn find_page_index(
+        script: &'a Script,
+        info: &'a script::Page,
+        expected: &'a str,
+    ) -> Option<usize> {
+        // Indices are offset by one, since they are meant to be read by humans.
+        info.find(&script.pages, expected)
+            .map(|position| position + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error::Error, Adventure};
+    use crate::script::Script;
+
+    #[test]
+    fn can_find_backlinks_and_neighbours() {
+        let input = "page first
+    title
+        First
+    link third
+        Skip ahead.
+    link third
+        Skip ahead again.
+
+page second
+    title
+        Second
+    link third
+        Onwards.
+
+page third
+    title
+        Third
+    link first
+        Back to the start.
+";
+
+        let script = Script::new(input, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let [first, second, third] = adventure.pages.as_slice() else {
+            panic!("Wrong number of pages!");
+        };
+
+        let backlinks: Vec<_> = third.backlinks.iter().map(|l| (l.index, l.text)).collect();
+        assert_eq!(vec![(1, "First"), (2, "Second")], backlinks);
+        assert_eq!(1, first.backlinks.len());
+        assert!(second.backlinks.is_empty());
+
+        assert!(first.prev.is_none());
+        assert!(matches!(&first.next, Some(link) if link.index == 2 && link.text == "Second"));
+        assert!(matches!(&third.prev, Some(link) if link.index == 2 && link.text == "Second"));
+        assert!(third.next.is_none());
+    }
+
+    #[test]
+    fn can_pass_conditions_to_templates() {
+        let input = "page hall
+    title
+        Hall
+    text
+        A hall.
+    text if not first-visit
+        The hall again.
+    link cellar if visited(hall)
+        Go down.
+
+page cellar
+    title
+        Cellar
+";
+
+        let script = Script::new(input, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let value = serde_json::to_value(&adventure.pages[0]).unwrap();
+
+        assert_eq!(
+            serde_json::json!([
+                { "text": "A hall." },
+                { "text": "The hall again.", "condition": "not first-visit" },
+            ]),
+            value["text"]
+        );
+        assert_eq!(
+            serde_json::json!([{ "index": 2, "text": "Go down.", "condition": "visited(hall)" }]),
+            value["links"]
+        );
+    }
+
+    #[test]
+    fn can_pass_link_modifiers_to_templates() {
+        let input = "page hall
+    title
+        Hall
+    link cellar once hidden-unless lamp
+        Go down.
+    link attic disabled \"The ladder is broken.\"
+        Climb up.
+
+page cellar
+    title
+        Cellar
+
+page attic
+    title
+        Attic
+";
+
+        let script = Script::new(input, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let value = serde_json::to_value(&adventure.pages[0]).unwrap();
+
+        assert_eq!(
+            serde_json::json!([
+                { "index": 2, "text": "Go down.", "once": true, "hidden_unless": "lamp" },
+                { "index": 3, "text": "Climb up.", "disabled": "The ladder is broken." },
+            ]),
+            value["links"]
+        );
+    }
+
+    #[test]
+    fn report_conditions_on_unknown_pages() {
+        let input = "page hall
+    title
+        Hall
+    text if visited(attic)
+        You've been upstairs.
+";
+
+        let script = Script::new(input, None).unwrap();
+        let errors = Adventure::new(&script).unwrap_err();
+
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::BadCondition { page, visited } if page == "hall" && visited == "attic"
+        ));
+    }
+
+    #[test]
+    fn report_unknown_templates() {
+        let input = "page hall
+    title
+        Hall
+    template wide
+    link attic
+        Upstairs.
+
+page attic
+    title
+        Attic
+    template template
+";
+
+        let script = Script::new(input, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        assert!(adventure.check_templates(&["template", "wide"]).is_ok());
+
+        let errors = adventure
+            .check_templates(&["template", "narrow"])
+            .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::UnknownTemplate { page, template } if page == "hall" && template == "wide"
+        ));
+    }
+
+    #[test]
+    fn can_resolve_pages_within_chapters() {
+        let input = "page cave
+    title
+        Outside
+    link chapter2/cave
+        Go in.
+
+chapter chapter2
+    title
+        Into the Dark
+    page cave
+        title
+            The Cave
+        link tunnel if not visited(tunnel)
+            Crawl on.
+
+    page tunnel
+        title
+            The Tunnel
+        link cave
+            Crawl back.
+";
+
+        let script = Script::new(input, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let [outside, cave, tunnel] = adventure.pages.as_slice() else {
+            panic!("Wrong number of pages!");
+        };
+
+        assert!(outside.chapter.is_none());
+        assert_eq!(2, outside.links[0].index);
+        assert_eq!(3, cave.links[0].index);
+        assert_eq!(2, tunnel.links[0].index);
+
+        let value = serde_json::to_value(cave).unwrap();
+        assert_eq!("chapter2/cave", value["identifier"]);
+        assert_eq!(
+            "not visited(chapter2/tunnel)",
+            value["links"][0]["condition"]
+        );
+        assert_eq!(
+            serde_json::json!({ "name": "chapter2", "title": "Into the Dark", "order": 1 }),
+            value["chapter"]
+        );
 
-        None
+        assert_eq!("1.html", outside.file_name("html", true));
+        assert_eq!("chapter2/2.html", cave.file_name("html", true));
+        assert_eq!("2.html", cave.file_name("html", false));
+        assert_eq!("../", cave.root(true));
     }
 }
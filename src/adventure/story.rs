@@ -0,0 +1,119 @@
+use super::Adventure;
+use crate::config;
+use serde::Serialize;
+
+/// Information about the whole adventure, passed to every page.
+#[derive(Debug, Serialize)]
+pub struct Story<'a> {
+    /// The information given in the config.
+    #[serde(flatten)]
+    pub info: &'a config::Story,
+
+    /// The total number of pages.
+    pub page_count: usize,
+
+    /// The index of the first page, if there are any pages.
+    pub start: Option<usize>,
+
+    /// The time of the build, as an RFC 3339 timestamp in UTC.
+    pub timestamp: String,
+
+    /// A UUID identifying the adventure, made from its title and page identifiers so it stays the
+    /// same between builds.
+    pub identifier: String,
+}
+
+impl<'a> Story<'a> {
+    /// Create story information for an adventure, built at the given number of seconds since the
+    /// Unix epoch.
+    pub fn new(info: &'a config::Story, adventure: &Adventure, time: u64) -> Self {
+        Self {
+            info,
+            page_count: adventure.pages.len(),
+            start: adventure.pages.first().map(|page| page.index),
+            timestamp: Self::format_timestamp(time),
+            identifier: Self::make_identifier(info, adventure),
+        }
+    }
+
+    /// Make a UUID from a hash of the title and page identifiers of an adventure.
+    fn make_identifier(info: &config::Story, adventure: &Adventure) -> String {
+        let title = info.title.as_deref().unwrap_or_default();
+        let identifiers = adventure.pages.iter().map(|page| page.identifier);
+        let parts: Vec<_> = std::iter::once(title).chain(identifiers).collect();
+
+        let high = fnv1a(0xcbf2_9ce4_8422_2325, &parts);
+        let low = fnv1a(0x8422_2325_cbf2_9ce4, &parts);
+
+        // Mark the UUID as version 4 (random), with the standard variant.
+        let high = (high & !0xf000) | 0x4000;
+        let low = (low & !(0xc << 60)) | (0x8 << 60);
+
+        format!(
+            "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+            high >> 32,
+            (high >> 16) & 0xffff,
+            high & 0xffff,
+            low >> 48,
+            low & 0xffff_ffff_ffff
+        )
+    }
+
+    /// Format a number of seconds since the Unix epoch as an RFC 3339 timestamp in UTC.
+    fn format_timestamp(time: u64) -> String {
+        let (days, seconds) = (time / 86400, time % 86400);
+
+        // Convert days since the epoch to a civil date, using the algorithm described at
+        // <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+        let days = days + 719468;
+        let era = days / 146097;
+        let day_of_era = days % 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+/// Hash some strings with the 64-bit FNV-1a algorithm, starting from the given basis.
+fn fnv1a(basis: u64, parts: &[&str]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.bytes().chain([0]))
+        .fold(basis, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Story;
+
+    #[test]
+    fn can_format_timestamps() {
+        const EXAMPLES: &[(u64, &str)] = &[
+            (0, "1970-01-01T00:00:00Z"),
+            (951782400, "2000-02-29T00:00:00Z"),
+            (1700000000, "2023-11-14T22:13:20Z"),
+            (1735689599, "2024-12-31T23:59:59Z"),
+        ];
+
+        for (input, expected) in EXAMPLES {
+            assert_eq!(*expected, Story::format_timestamp(*input));
+        }
+    }
+}
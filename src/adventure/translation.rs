@@ -0,0 +1,248 @@
+use super::error::Error;
+use crate::script::{Link, Page, Random, Script};
+
+/// Check that a translated script has the same pages, in the same order, with the same links as the
+/// original, so that every translation is built with the same page indices.
+pub fn check(locale: &str, original: &Script, translation: &Script) -> Result<(), Vec<Error>> {
+    let mut errors = Vec::new();
+
+    for page in &original.pages {
+        match find_page(translation, &page.identifier) {
+            Some(translated) if !same_links(page, translated) => {
+                errors.push(Error::translation_links(locale, &page.identifier));
+            }
+            Some(_) => {}
+            None => errors.push(Error::missing_translation(locale, &page.identifier)),
+        }
+    }
+
+    for page in &translation.pages {
+        if find_page(original, &page.identifier).is_none() {
+            errors.push(Error::unexpected_translation(locale, &page.identifier));
+        }
+    }
+
+    // Only bother checking the order when both scripts have the same pages, otherwise every page
+    // after the first missing one would be reported.
+    if errors.is_empty() {
+        let misplaced = original
+            .pages
+            .iter()
+            .zip(&translation.pages)
+            .find(|(page, translated)| page.identifier != translated.identifier);
+
+        if let Some((_, translated)) = misplaced {
+            errors.push(Error::translation_order(locale, &translated.identifier));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn find_page<'a, 'b>(script: &'b Script<'a>, identifier: &str) -> Option<&'b Page<'a>> {
+    script
+        .pages
+        .iter()
+        .find(|page| page.identifier == identifier)
+}
+
+/// Check that two pages link to the same pages, in the same order, on the same conditions and with
+/// the same modifiers, and that their random choices have the same outcomes, with the same weights.
+/// Their paragraphs must have the same conditions too. Only the text of the paragraphs, links and
+/// choices, and the reasons links are disabled, may differ.
+fn same_links(page: &Page, translated: &Page) -> bool {
+    fn untranslated<'a>(link: &Link<'a>) -> Link<'a> {
+        Link {
+            text: "",
+            disabled: link.disabled.map(|_| ""),
+            ..link.clone()
+        }
+    }
+
+    fn outcomes<'a>(choice: &'a Random) -> impl Iterator<Item = (&'a str, u32)> + 'a {
+        let outcomes = choice.outcomes.iter();
+        outcomes.map(|outcome| (outcome.target.as_ref(), outcome.weight))
+    }
+
+    let links = page.links.iter().map(untranslated);
+    let same_random = page.random.len() == translated.random.len()
+        && page
+            .random
+            .iter()
+            .zip(&translated.random)
+            .all(|(choice, other)| {
+                choice.condition == other.condition && outcomes(choice).eq(outcomes(other))
+            });
+
+    links.eq(translated.links.iter().map(untranslated))
+        && same_random
+        && page.paragraph_conditions == translated.paragraph_conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Error};
+    use crate::script::Script;
+
+    const ORIGINAL: &str = "page start
+    title
+        Start
+    link end
+        Finish.
+
+page end
+    title
+        The End
+";
+
+    #[test]
+    fn can_accept_matching_translation() {
+        let translation = "page start
+    title
+        Anfang
+    link end
+        Beenden.
+
+page end
+    title
+        Ende
+";
+
+        let original = Script::new(ORIGINAL, None).unwrap();
+        let translation = Script::new(translation, None).unwrap();
+
+        assert!(check("de", &original, &translation).is_ok());
+    }
+
+    #[test]
+    fn report_missing_and_unexpected_pages() {
+        let translation = "page start
+    title
+        Anfang
+    link end
+        Beenden.
+
+page ende
+    title
+        Ende
+";
+
+        let original = Script::new(ORIGINAL, None).unwrap();
+        let translation = Script::new(translation, None).unwrap();
+        let errors = check("de", &original, &translation).unwrap_err();
+
+        assert_eq!(2, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::MissingTranslation { locale, page } if locale == "de" && page == "end"
+        ));
+        assert!(matches!(
+            &errors[1],
+            Error::UnexpectedTranslation { locale, page } if locale == "de" && page == "ende"
+        ));
+    }
+
+    #[test]
+    fn report_reordered_pages() {
+        let translation = "page end
+    title
+        Ende
+
+page start
+    title
+        Anfang
+    link end
+        Beenden.
+";
+
+        let original = Script::new(ORIGINAL, None).unwrap();
+        let translation = Script::new(translation, None).unwrap();
+        let errors = check("de", &original, &translation).unwrap_err();
+
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::TranslationOrder { page, .. } if page == "end"
+        ));
+    }
+
+    #[test]
+    fn report_different_paragraph_conditions() {
+        let original = "page start
+    title
+        Start
+    text
+        Hello.
+    text if first-visit
+        Welcome!
+";
+        let translation = original.replace("if first-visit", "if not first-visit");
+
+        let original = Script::new(original, None).unwrap();
+        let translation = Script::new(&translation, None).unwrap();
+        let errors = check("de", &original, &translation).unwrap_err();
+
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::TranslationLinks { page, .. } if page == "start"
+        ));
+    }
+
+    #[test]
+    fn report_different_random_outcomes() {
+        let original = "page start
+    title
+        Start
+    random
+        Roll the dice.
+        link end weight 5
+            Win.
+        link start
+            Lose.
+
+page end
+    title
+        The End
+";
+        let translation = original.replace("weight 5", "weight 2");
+
+        let original = Script::new(original, None).unwrap();
+        let translation = Script::new(&translation, None).unwrap();
+        let errors = check("de", &original, &translation).unwrap_err();
+
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::TranslationLinks { page, .. } if page == "start"
+        ));
+    }
+
+    #[test]
+    fn report_different_links() {
+        let translation = "page start
+    title
+        Anfang
+    link start
+        Nochmal.
+
+page end
+    title
+        Ende
+";
+
+        let original = Script::new(ORIGINAL, None).unwrap();
+        let translation = Script::new(translation, None).unwrap();
+        let errors = check("de", &original, &translation).unwrap_err();
+
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            Error::TranslationLinks { page, .. } if page == "start"
+        ));
+    }
+}
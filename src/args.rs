@@ -1,12 +1,98 @@
-use clap::Parser;
+use clap::{ArgEnum, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
     /// The path to the configuration file used to generate the adventure.
-    pub config: PathBuf,
+    #[clap(required = true)]
+    pub config: Option<PathBuf>,
 
     /// The directory to write output files to.
-    pub output: PathBuf,
+    #[clap(required = true)]
+    pub output: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Extract the text of the script to a gettext PO file for translation.
+    Extract {
+        /// The path to the configuration file naming the script.
+        config: PathBuf,
+
+        /// The PO file to write.
+        catalog: PathBuf,
+    },
+
+    /// Create a translated script from the script and a translated PO file.
+    Import {
+        /// The path to the configuration file naming the script.
+        config: PathBuf,
+
+        /// The translated PO file.
+        catalog: PathBuf,
+
+        /// The translated script to write.
+        output: PathBuf,
+    },
+
+    /// Export the adventure to another format.
+    Export {
+        /// The path to the configuration file used to generate the adventure.
+        config: PathBuf,
+
+        /// The file to write.
+        output: PathBuf,
+
+        /// The format to export to.
+        #[clap(long, arg_enum, default_value_t = ExportFormat::Twee)]
+        format: ExportFormat,
+
+        /// Shuffle the numbered sections of EPUB output, so they can't be read straight through.
+        /// Printed gamebooks are always shuffled.
+        #[clap(long)]
+        shuffle: bool,
+
+        /// The seed to shuffle sections with, 0 if not given. The same seed always gives the same
+        /// order.
+        #[clap(long)]
+        seed: Option<u64>,
+    },
+
+    /// Create a script from a Twee 3 story, as written by Twine.
+    ImportTwee {
+        /// The Twee file to read.
+        input: PathBuf,
+
+        /// The script to write.
+        output: PathBuf,
+    },
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+pub enum ExportFormat {
+    /// Twee 3, the text format of Twine stories.
+    Twee,
+
+    /// Ink, the narrative scripting language by Inkle.
+    Ink,
+
+    /// An EPUB 3 gamebook, with each page as a numbered section.
+    Epub,
+
+    /// A print-ready HTML gamebook, with shuffled section numbers.
+    Gamebook,
+
+    /// A plain text gamebook manuscript, with shuffled section numbers.
+    Manuscript,
+
+    /// A Markdown document of the whole adventure, for reviewing.
+    Markdown,
+
+    /// A plain text document of the whole adventure, for reviewing.
+    Text,
 }
@@ -0,0 +1,661 @@
+//! Translation catalogs, so scripts can be translated with standard gettext tools.
+
+pub mod po;
+
+use crate::script::parse::{
+    syntax::{SyntaxLine, SyntaxNode, SyntaxTree},
+    DirectiveKind, LineKind,
+};
+use po::Entry;
+use std::collections::{HashMap, HashSet};
+
+/// A piece of translatable text within a script.
+struct Message<'t, 'a> {
+    /// Identifies the text by its page, what it is, and its position within the page, such as
+    /// `start/title`, `start/paragraph/1`, `start/link/0` or `start/random/0`. The text of snippets
+    /// is identified by the name of the snippet instead, such as `snippet:exits/0`, and the titles
+    /// of chapters by the name of the chapter, such as `chapter:chapter2/title`. The reasons links
+    /// are disabled follow the link, such as `start/link/0/disabled`, and the values given to
+    /// snippets are identified by their position among the uses and their parameter, such as
+    /// `start/use/0/way`.
+    context: String,
+
+    /// The line of the script holding the text.
+    line: &'t SyntaxLine<'a>,
+
+    /// The text itself, which is the content of the line, or part of it for the reason a link is
+    /// disabled or the value of a parameter.
+    text: &'a str,
+
+    /// Where the text starts within the content of the line.
+    offset: usize,
+
+    /// Comments directly above the text, or the directive containing it.
+    comments: Vec<&'a str>,
+}
+
+/// Find every title, paragraph, link, random choice, snippet and chapter text of a script, in
+/// order.
+fn messages<'t, 'a>(tree: &'t SyntaxTree<'a>) -> Vec<Message<'t, 'a>> {
+    let mut messages = Vec::new();
+
+    for node in &tree.nodes {
+        match node.line.kind {
+            LineKind::Directive(DirectiveKind::Page, Some(argument)) => {
+                push_page(&mut messages, node, page_identifier(argument));
+            }
+            // The text of a snippet is translated once, wherever it's used.
+            LineKind::Directive(DirectiveKind::Snippet, Some(argument)) => {
+                let name = argument.split_whitespace().next().unwrap_or_default();
+                let mut position = 0;
+                let context = || {
+                    position += 1;
+                    format!("snippet:{name}/{}", position - 1)
+                };
+
+                push_texts(&mut messages, node, context);
+                push_uses(&mut messages, node, &format!("snippet:{name}"), &mut 0);
+            }
+            // Pages within chapters are identified along with the chapter, as they are in links.
+            LineKind::Directive(DirectiveKind::Chapter, Some(name)) => {
+                for child in &node.children {
+                    match child.line.kind {
+                        LineKind::Directive(DirectiveKind::Title, _) => {
+                            push_texts(&mut messages, child, || format!("chapter:{name}/title"));
+                        }
+                        LineKind::Directive(DirectiveKind::Page, Some(argument)) => {
+                            let identifier = format!("{name}/{}", page_identifier(argument));
+                            push_page(&mut messages, child, &identifier);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    messages
+}
+
+/// The identifier of a page from the argument of its directive, without the page it extends.
+fn page_identifier(argument: &str) -> &str {
+    argument
+        .split_once(" extends ")
+        .map_or(argument, |(identifier, _)| identifier.trim_end())
+}
+
+/// Add a message for each title, paragraph, link and random choice of a page.
+fn push_page<'t, 'a>(
+    messages: &mut Vec<Message<'t, 'a>>,
+    page: &'t SyntaxNode<'a>,
+    identifier: &str,
+) {
+    let mut paragraphs = 0;
+    let mut links = 0;
+    let mut random = 0;
+    let mut uses = 0;
+
+    // The links of random choices are nested within them, and come straight after them.
+    let blocks = page.children.iter().flat_map(|block| {
+        let nested = match block.line.kind {
+            LineKind::Directive(DirectiveKind::Random, _) => block.children.as_slice(),
+            _ => &[],
+        };
+        std::iter::once(block).chain(nested)
+    });
+
+    for block in blocks {
+        let link = links;
+        let (name, mut position) = match block.line.kind {
+            LineKind::Directive(DirectiveKind::Title, _) => ("title", None),
+            LineKind::Directive(DirectiveKind::Text, _) => ("paragraph", Some(&mut paragraphs)),
+            LineKind::Directive(DirectiveKind::Link, _) => ("link", Some(&mut links)),
+            LineKind::Directive(DirectiveKind::Random, _) => ("random", Some(&mut random)),
+            _ => continue,
+        };
+
+        let context = || match &mut position {
+            Some(position) => {
+                let context = format!("{identifier}/{name}/{position}");
+                **position += 1;
+                context
+            }
+            None => format!("{identifier}/{name}"),
+        };
+
+        push_texts(messages, block, context);
+        if name == "paragraph" {
+            push_uses(messages, block, identifier, &mut uses);
+        }
+
+        // The reason a link is disabled is within the directive, after the link's text.
+        if name == "link" {
+            if let Some((offset, reason)) = disabled_reason(block.syntax.content) {
+                messages.push(Message {
+                    context: format!("{identifier}/link/{link}/disabled"),
+                    line: &block.syntax,
+                    text: reason,
+                    offset,
+                    comments: comments(block, None),
+                });
+            }
+        }
+    }
+}
+
+/// Add a message for the value of each parameter given to each snippet used directly within a
+/// block, numbering the uses from the given count.
+fn push_uses<'t, 'a>(
+    messages: &mut Vec<Message<'t, 'a>>,
+    block: &'t SyntaxNode<'a>,
+    prefix: &str,
+    count: &mut usize,
+) {
+    let uses = block
+        .children
+        .iter()
+        .filter(|node| matches!(node.line.kind, LineKind::Directive(DirectiveKind::Use, _)));
+
+    for use_ in uses {
+        // Each value follows the name of its parameter, such as `way north`.
+        for argument in &use_.children {
+            let content = argument.syntax.content;
+            let (parameter, value) = content
+                .split_once(char::is_whitespace)
+                .unwrap_or((content, ""));
+            let value = value.trim_start();
+
+            if !value.is_empty() {
+                messages.push(Message {
+                    context: format!("{prefix}/use/{count}/{parameter}"),
+                    line: &argument.syntax,
+                    text: value,
+                    offset: content.len() - value.len(),
+                    comments: comments(use_, Some(argument)),
+                });
+            }
+        }
+
+        *count += 1;
+    }
+}
+
+/// Find the reason in a link directive such as `link cave disabled "The door is locked"`, along
+/// with where it starts in the line.
+fn disabled_reason(content: &str) -> Option<(usize, &str)> {
+    let after = &content[content.find(" disabled ")? + " disabled ".len()..];
+    let start = content.len() - after.trim_start().strip_prefix('"')?.len();
+    let length = content[start..].find('"')?;
+    Some((start, &content[start..start + length]))
+}
+
+/// The comments directly above a block, followed by those above a line of text within it.
+fn comments<'a>(block: &SyntaxNode<'a>, text: Option<&SyntaxNode<'a>>) -> Vec<&'a str> {
+    let text = text.map(|text| text.leading.as_slice()).unwrap_or_default();
+    block
+        .leading
+        .iter()
+        .chain(text)
+        .filter_map(SyntaxLine::comment)
+        .map(|comment| comment.trim_start_matches('#').trim())
+        .collect()
+}
+
+/// Add a message for each line of text directly within a block, with contexts from the given
+/// function.
+fn push_texts<'t, 'a>(
+    messages: &mut Vec<Message<'t, 'a>>,
+    block: &'t SyntaxNode<'a>,
+    mut context: impl FnMut() -> String,
+) {
+    let texts = block
+        .children
+        .iter()
+        .filter(|node| matches!(node.line.kind, LineKind::Text(_)));
+
+    for text in texts {
+        messages.push(Message {
+            context: context(),
+            line: &text.syntax,
+            text: text.syntax.content,
+            offset: 0,
+            comments: comments(block, Some(text)),
+        });
+    }
+}
+
+/// Extract the translatable text of a script as the entries of a PO file. References to the script
+/// use the given name.
+pub fn extract(tree: &SyntaxTree, name: &str) -> Vec<Entry> {
+    let entries = messages(tree).into_iter().map(|message| Entry {
+        comments: message.comments.into_iter().map(str::to_owned).collect(),
+        references: vec![format!("{name}:{}", message.line.number + 1)],
+        context: Some(message.context),
+        id: message.text.to_owned(),
+        ..Entry::default()
+    });
+
+    std::iter::once(Entry::header()).chain(entries).collect()
+}
+
+/// The result of applying a catalog to a script.
+#[derive(Debug)]
+pub struct Import {
+    /// The translated script.
+    pub script: String,
+
+    /// Messages without a usable translation, which keep their original text.
+    pub missing: Vec<String>,
+
+    /// Translations of text which has since changed or been removed from the script.
+    pub stale: Vec<String>,
+
+    /// Translations which can't be written to the script, since they would be read as something
+    /// other than text, so the original text is kept instead.
+    pub unwritable: Vec<String>,
+}
+
+/// Whether a translation can be written in place of a message. Text starting with the name of a
+/// directive would be read as that directive, and everything after a `#` as a comment, while a
+/// quote would end quoted text, such as the reason a link is disabled, early.
+fn is_writable(message: &Message, text: &str) -> bool {
+    if text.contains('#') {
+        return false;
+    }
+
+    if message.offset > 0 {
+        let quoted = message.line.content[..message.offset].ends_with('"');
+        !quoted || !text.contains('"')
+    } else {
+        let word = text.split_whitespace().next().unwrap_or_default();
+        DirectiveKind::from_str(word).is_none()
+    }
+}
+
+/// Create a translated script from the entries of a PO file. Everything other than the translated
+/// text, such as comments and blank lines, is kept as it is.
+pub fn import(tree: &SyntaxTree, entries: &[Entry]) -> Import {
+    let translations: HashMap<_, _> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.context.as_deref()?, entry)))
+        .collect();
+
+    let messages = messages(tree);
+    let mut replacements = HashMap::new();
+    let mut missing = Vec::new();
+    let mut stale = Vec::new();
+    let mut unwritable = Vec::new();
+
+    for message in &messages {
+        match translations.get(message.context.as_str()) {
+            Some(entry) if entry.id != message.text => {
+                stale.push(message.context.clone());
+                missing.push(message.context.clone());
+            }
+            Some(entry) if !entry.fuzzy && !entry.translation.trim().is_empty() => {
+                // The translation has to stay on a single line to keep the script's structure.
+                let text = entry.translation.split_whitespace().collect::<Vec<_>>();
+                let text = text.join(" ");
+
+                if !is_writable(message, &text) {
+                    unwritable.push(message.context.clone());
+                    continue;
+                }
+
+                let content = message.line.content;
+                let end = message.offset + message.text.len();
+                let replaced = format!("{}{text}{}", &content[..message.offset], &content[end..]);
+                replacements.insert(message.line.number, replaced);
+            }
+            _ => missing.push(message.context.clone()),
+        }
+    }
+
+    let known: HashSet<_> = messages.iter().map(|m| m.context.as_str()).collect();
+    stale.extend(
+        entries
+            .iter()
+            .filter_map(|entry| entry.context.as_deref())
+            .filter(|context| !known.contains(context))
+            .map(str::to_owned),
+    );
+
+    let mut script = String::new();
+    for node in &tree.nodes {
+        write_node(&mut script, node, &replacements);
+    }
+    for line in &tree.trailing {
+        script.push_str(&line.to_string());
+    }
+
+    Import {
+        script,
+        missing,
+        stale,
+        unwritable,
+    }
+}
+
+/// Write a node of a script, replacing the content of lines with the given numbers.
+fn write_node(output: &mut String, node: &SyntaxNode, replacements: &HashMap<usize, String>) {
+    for line in &node.leading {
+        output.push_str(&line.to_string());
+    }
+
+    let line = &node.syntax;
+    match replacements.get(&line.number) {
+        Some(content) => {
+            output.push_str(line.indent);
+            output.push_str(content);
+            output.push_str(line.trailing);
+            output.push_str(line.ending);
+        }
+        None => output.push_str(&line.to_string()),
+    }
+
+    for child in &node.children {
+        write_node(output, child, replacements);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, import, po::Entry};
+    use crate::script::parse::syntax::SyntaxTree;
+
+    const SCRIPT: &str = "# The first page.
+page start
+    title
+        Start
+    text
+        One.
+
+        Two.
+    # Keep this short.
+    link end # Leave.
+        Finish.
+
+page end
+    title
+        The End
+";
+
+    fn entry(context: &str, id: &str, translation: &str) -> Entry {
+        Entry {
+            context: Some(context.to_owned()),
+            id: id.to_owned(),
+            translation: translation.to_owned(),
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn can_extract_messages() {
+        let tree = SyntaxTree::parse(SCRIPT, None).unwrap();
+        let entries = extract(&tree, "script.txt");
+
+        assert!(entries[0].is_header());
+
+        let messages: Vec<_> = entries[1..]
+            .iter()
+            .map(|e| {
+                (
+                    e.context.as_deref().unwrap(),
+                    e.id.as_str(),
+                    e.references[0].as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("start/title", "Start", "script.txt:4"),
+                ("start/paragraph/0", "One.", "script.txt:6"),
+                ("start/paragraph/1", "Two.", "script.txt:8"),
+                ("start/link/0", "Finish.", "script.txt:11"),
+                ("end/title", "The End", "script.txt:15"),
+            ],
+            messages
+        );
+
+        assert_eq!(vec!["Keep this short."], entries[4].comments);
+        assert!(entries[1].comments.is_empty());
+    }
+
+    #[test]
+    fn can_extract_random_choices() {
+        let script = "page start
+    title
+        Start
+    random
+        Roll.
+        link start
+            Again.
+    link start
+        Stay.
+";
+        let tree = SyntaxTree::parse(script, None).unwrap();
+        let entries = extract(&tree, "script.txt");
+
+        let messages: Vec<_> = entries[1..]
+            .iter()
+            .map(|e| (e.context.as_deref().unwrap(), e.id.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("start/title", "Start"),
+                ("start/random/0", "Roll."),
+                ("start/link/0", "Again."),
+                ("start/link/1", "Stay."),
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn can_extract_snippets() {
+        let script = "snippet exits way
+    Exits lead {way}.
+    use sign
+
+page start
+    title
+        Start
+    text
+        use exits
+            way north
+        A room.
+";
+        let tree = SyntaxTree::parse(script, None).unwrap();
+        let entries = extract(&tree, "script.txt");
+
+        let messages: Vec<_> = entries[1..]
+            .iter()
+            .map(|e| (e.context.as_deref().unwrap(), e.id.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("snippet:exits/0", "Exits lead {way}."),
+                ("start/title", "Start"),
+                ("start/paragraph/0", "A room."),
+                ("start/use/0/way", "north"),
+            ],
+            messages
+        );
+
+        let entries = [
+            Entry::header(),
+            entry(
+                "snippet:exits/0",
+                "Exits lead {way}.",
+                "Ausgänge führen {way}.",
+            ),
+            entry("start/use/0/way", "north", "nach Norden"),
+        ];
+
+        let result = import(&tree, &entries);
+
+        assert!(result
+            .script
+            .contains("        use exits\n            way nach Norden\n"));
+        assert!(result.script.contains("    Ausgänge führen {way}.\n"));
+    }
+
+    #[test]
+    fn can_extract_chapters() {
+        let script = "chapter chapter2
+    title
+        Into the Dark
+    page cave extends hall
+        title
+            The Cave
+";
+        let tree = SyntaxTree::parse(script, None).unwrap();
+        let entries = extract(&tree, "script.txt");
+
+        let messages: Vec<_> = entries[1..]
+            .iter()
+            .map(|e| (e.context.as_deref().unwrap(), e.id.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("chapter:chapter2/title", "Into the Dark"),
+                ("chapter2/cave/title", "The Cave"),
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn can_import_translations() {
+        let tree = SyntaxTree::parse(SCRIPT, None).unwrap();
+        let entries = [
+            Entry::header(),
+            entry("start/title", "Start", "Anfang"),
+            entry("start/paragraph/0", "One.", "Eins."),
+            entry("start/paragraph/1", "Two.", "Zwei\nund mehr."),
+            entry("start/link/0", "Finish.", "Beenden."),
+            entry("end/title", "The End", "Das Ende"),
+        ];
+
+        let result = import(&tree, &entries);
+
+        let expected = "# The first page.
+page start
+    title
+        Anfang
+    text
+        Eins.
+
+        Zwei und mehr.
+    # Keep this short.
+    link end # Leave.
+        Beenden.
+
+page end
+    title
+        Das Ende
+";
+
+        assert_eq!(expected, result.script);
+        assert!(result.missing.is_empty());
+        assert!(result.stale.is_empty());
+    }
+
+    #[test]
+    fn can_translate_disabled_links() {
+        let script = "page start
+    link cave disabled \"The door is locked.\" if lamp
+        Enter the cave.
+";
+        let tree = SyntaxTree::parse(script, None).unwrap();
+        let entries = extract(&tree, "script.txt");
+
+        let messages: Vec<_> = entries[1..]
+            .iter()
+            .map(|e| (e.context.as_deref().unwrap(), e.id.as_str()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("start/link/0", "Enter the cave."),
+                ("start/link/0/disabled", "The door is locked."),
+            ],
+            messages
+        );
+
+        let entries = [
+            Entry::header(),
+            entry("start/link/0", "Enter the cave.", "Betritt die Höhle."),
+            entry(
+                "start/link/0/disabled",
+                "The door is locked.",
+                "Die Tür ist verschlossen.",
+            ),
+        ];
+
+        let result = import(&tree, &entries);
+
+        let expected = "page start
+    link cave disabled \"Die Tür ist verschlossen.\" if lamp
+        Betritt die Höhle.
+";
+
+        assert_eq!(expected, result.script);
+        assert!(result.missing.is_empty());
+    }
+
+    #[test]
+    fn can_report_missing_and_stale_translations() {
+        let tree = SyntaxTree::parse(SCRIPT, None).unwrap();
+
+        let mut fuzzy = entry("start/link/0", "Finish.", "Beenden.");
+        fuzzy.fuzzy = true;
+
+        let entries = [
+            entry("start/title", "Start", "Anfang"),
+            entry("start/paragraph/0", "Uno.", "Eins."),
+            entry("start/paragraph/1", "Two.", ""),
+            fuzzy,
+            entry("cellar/title", "Cellar", "Keller"),
+        ];
+
+        let result = import(&tree, &entries);
+
+        assert_eq!(
+            vec![
+                "start/paragraph/0",
+                "start/paragraph/1",
+                "start/link/0",
+                "end/title"
+            ],
+            result.missing
+        );
+        assert_eq!(vec!["start/paragraph/0", "cellar/title"], result.stale);
+        assert!(result.script.contains("Anfang"));
+        assert!(result.script.contains("One."));
+    }
+
+    #[test]
+    fn report_unwritable_translations() {
+        let tree = SyntaxTree::parse(SCRIPT, None).unwrap();
+        let entries = [
+            entry("start/title", "Start", "page eins"),
+            entry("start/paragraph/0", "One.", "Nummer #1."),
+            entry("start/paragraph/1", "Two.", "Link\nzwei."),
+            entry("start/link/0", "Finish.", "Beenden."),
+        ];
+
+        let result = import(&tree, &entries);
+
+        assert_eq!(vec!["start/title", "start/paragraph/0"], result.unwritable);
+        assert!(result.script.contains("        Start\n"));
+        assert!(result.script.contains("        One.\n"));
+        assert!(result.script.contains("        Link zwei.\n"));
+        assert!(SyntaxTree::parse(&result.script, None).is_ok());
+    }
+}
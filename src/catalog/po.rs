@@ -0,0 +1,354 @@
+//! Reading and writing of gettext PO files.
+
+/// A single message of a PO file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Entry {
+    /// Notes for translators, taken from the comments of the script.
+    pub comments: Vec<String>,
+
+    /// Source references, such as `script.txt:12`.
+    pub references: Vec<String>,
+
+    /// Whether the translation is marked as fuzzy, and so shouldn't be used.
+    pub fuzzy: bool,
+
+    /// The context of the message, which we use as a stable identifier.
+    pub context: Option<String>,
+
+    /// The original text.
+    pub id: String,
+
+    /// The translated text, empty if not yet translated.
+    pub translation: String,
+}
+
+impl Entry {
+    /// Create the header entry of a PO file, which describes the file itself.
+    pub fn header() -> Self {
+        Self {
+            translation: "Content-Type: text/plain; charset=UTF-8\n".to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether this is the header entry of a PO file.
+    pub fn is_header(&self) -> bool {
+        self.context.is_none() && self.id.is_empty()
+    }
+
+    /// Get the value of a field of the header entry, such as `Language`.
+    pub fn header_field(&self, name: &str) -> Option<&str> {
+        self.translation.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim())
+        })
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for comment in &self.comments {
+            writeln!(f, "#. {comment}")?;
+        }
+
+        for reference in &self.references {
+            writeln!(f, "#: {reference}")?;
+        }
+
+        if self.fuzzy {
+            writeln!(f, "#, fuzzy")?;
+        }
+
+        if let Some(context) = &self.context {
+            writeln!(f, "msgctxt \"{}\"", escape(context))?;
+        }
+
+        writeln!(f, "msgid \"{}\"", escape(&self.id))?;
+        writeln!(f, "msgstr \"{}\"", escape(&self.translation))
+    }
+}
+
+/// Write a list of entries as a PO file.
+pub fn write(entries: &[Entry]) -> String {
+    entries
+        .iter()
+        .map(Entry::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    for c in string.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// An error encountered while parsing a PO file.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    BadString,
+    UnexpectedString,
+    UnsupportedKeyword(String),
+    IncompleteEntry,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::BadString => write!(f, "Expected a quoted string."),
+            Error::UnexpectedString => write!(f, "A string must follow a keyword."),
+            Error::UnsupportedKeyword(keyword) => {
+                write!(f, "The {keyword} keyword is unsupported.")
+            }
+            Error::IncompleteEntry => write!(f, "An entry requires both a msgid and a msgstr."),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The part of an entry being read, which continuation strings are appended to.
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    Translation,
+}
+
+/// Partially read entry.
+#[derive(Default)]
+struct Builder {
+    comments: Vec<String>,
+    references: Vec<String>,
+    fuzzy: bool,
+    context: Option<String>,
+    id: Option<String>,
+    translation: Option<String>,
+    field: Option<Field>,
+    line: usize,
+}
+
+impl Builder {
+    fn is_empty(&self) -> bool {
+        self.context.is_none() && self.id.is_none() && self.translation.is_none()
+    }
+
+    fn append(&mut self, string: String) -> Result<(), Error> {
+        let target = match self.field {
+            Some(Field::Context) => &mut self.context,
+            Some(Field::Id) => &mut self.id,
+            Some(Field::Translation) => &mut self.translation,
+            None => return Err(Error::UnexpectedString),
+        };
+
+        target.get_or_insert_with(String::new).push_str(&string);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Entry, (usize, Error)> {
+        match (self.id, self.translation) {
+            (Some(id), Some(translation)) => Ok(Entry {
+                comments: self.comments,
+                references: self.references,
+                fuzzy: self.fuzzy,
+                context: self.context,
+                id,
+                translation,
+            }),
+            _ => Err((self.line, Error::IncompleteEntry)),
+        }
+    }
+}
+
+/// Parse a PO file. On failure, return the (zero-based) line number and the error.
+pub fn parse(input: &str) -> Result<Vec<Entry>, (usize, Error)> {
+    let mut entries = Vec::new();
+    let mut builder = Builder::default();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+
+        // A comment or keyword after the translation starts a new entry.
+        let starts_entry = line.starts_with('#') || line.starts_with("msg");
+        if starts_entry && builder.translation.is_some() {
+            entries.push(std::mem::take(&mut builder).finish()?);
+        }
+
+        if builder.is_empty() {
+            builder.line = number;
+        }
+
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        } else if let Some(comment) = line.strip_prefix("#.") {
+            builder.comments.push(comment.trim().to_owned());
+        } else if let Some(references) = line.strip_prefix("#:") {
+            builder
+                .references
+                .extend(references.split_whitespace().map(str::to_owned));
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            builder.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+        } else if line.starts_with('#') {
+            continue;
+        } else if line.starts_with('"') {
+            let string = unescape(line).map_err(|e| (number, e))?;
+            builder.append(string).map_err(|e| (number, e))?;
+        } else {
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+            let field = match keyword {
+                "msgctxt" => Field::Context,
+                "msgid" => Field::Id,
+                "msgstr" => Field::Translation,
+                other => return Err((number, Error::UnsupportedKeyword(other.to_owned()))),
+            };
+
+            // Each keyword may appear once per entry, in order.
+            let in_order = match field {
+                Field::Context => builder.is_empty(),
+                Field::Id => builder.id.is_none(),
+                Field::Translation => builder.id.is_some(),
+            };
+            if !in_order {
+                return Err((builder.line, Error::IncompleteEntry));
+            }
+
+            builder.field = Some(field);
+
+            let string = unescape(rest.trim()).map_err(|e| (number, e))?;
+            builder.append(string).map_err(|e| (number, e))?;
+        }
+    }
+
+    if !builder.is_empty() {
+        entries.push(builder.finish()?);
+    }
+
+    Ok(entries)
+}
+
+/// Read a quoted and escaped string.
+fn unescape(quoted: &str) -> Result<String, Error> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or(Error::BadString)?;
+
+    let mut string = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some('t') => string.push('\t'),
+            Some(c @ ('\\' | '"')) => string.push(c),
+            _ => return Err(Error::BadString),
+        }
+    }
+
+    Ok(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, write, Entry, Error};
+
+    #[test]
+    fn can_round_trip_entries() {
+        let entries = vec![
+            Entry::header(),
+            Entry {
+                comments: vec!["Shown at the top.".to_owned()],
+                references: vec!["script.txt:3".to_owned()],
+                fuzzy: false,
+                context: Some("start/title".to_owned()),
+                id: "The \"Start\"".to_owned(),
+                translation: "Der \\ Anfang".to_owned(),
+            },
+            Entry {
+                comments: Vec::new(),
+                references: Vec::new(),
+                fuzzy: true,
+                context: Some("start/paragraph/0".to_owned()),
+                id: "Hello.".to_owned(),
+                translation: String::new(),
+            },
+        ];
+
+        let output = parse(&write(&entries)).unwrap();
+        assert_eq!(entries, output);
+    }
+
+    #[test]
+    fn can_parse_multiline_strings_and_comments() {
+        let input = r#"# Translator comment
+msgid ""
+msgstr ""
+"Language: de\n"
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#: script.txt:5 script.txt:6
+#, fuzzy, c-format
+msgctxt "start/link/0"
+msgid ""
+"Go "
+"down."
+msgstr "Nach unten."
+
+#~ msgid "Obsolete"
+#~ msgstr "Veraltet"
+"#;
+
+        let entries = parse(input).unwrap();
+
+        assert_eq!(2, entries.len());
+        assert!(entries[0].is_header());
+        assert_eq!(Some("de"), entries[0].header_field("Language"));
+
+        assert_eq!(vec!["script.txt:5", "script.txt:6"], entries[1].references);
+        assert!(entries[1].fuzzy);
+        assert_eq!(Some("start/link/0"), entries[1].context.as_deref());
+        assert_eq!("Go down.", entries[1].id);
+        assert_eq!("Nach unten.", entries[1].translation);
+    }
+
+    #[test]
+    fn can_report_errors() {
+        const EXAMPLES: &[(&str, usize, Error)] = &[
+            ("msgid \"a\"\nmsgstr \"b", 1, Error::BadString),
+            ("\"a\"", 0, Error::UnexpectedString),
+            (
+                "msgid \"a\"\n\nmsgctxt \"b\"\nmsgid \"c\"\nmsgstr \"\"",
+                0,
+                Error::IncompleteEntry,
+            ),
+        ];
+
+        for (input, line, error) in EXAMPLES {
+            let result = parse(input).err();
+            assert_eq!(Some((*line, error)), result.as_ref().map(|(l, e)| (*l, e)));
+        }
+
+        assert!(matches!(
+            parse("msgid_plural \"a\""),
+            Err((0, Error::UnsupportedKeyword(keyword))) if keyword == "msgid_plural"
+        ));
+    }
+}
@@ -1,16 +1,197 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use crate::{escape::Escape, script::parse::Indentation};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeMap, path::PathBuf};
 
 /// A configuration of an adventure.
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
-    /// The path of the template file to use.
-    pub template: PathBuf,
+    /// The path of the template file to use. Only HTML output needs one.
+    pub template: Option<PathBuf>,
+
+    /// The kind of files to build the adventure into.
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    /// How text is escaped by templates, for producing formats other than HTML.
+    #[serde(default)]
+    pub escape: Escape,
+
+    /// The file extension of pages rendered from templates, without a leading dot.
+    #[serde(default = "default_extension")]
+    pub extension: String,
+
+    /// Whether to write the pages of each chapter to a directory named after it.
+    #[serde(default)]
+    pub chapter_directories: bool,
+
+    /// Additional templates which pages can select, by name.
+    pub templates: Option<BTreeMap<String, PathBuf>>,
+
+    /// Partial templates to register, by name.
+    pub partials: Option<BTreeMap<String, PathBuf>>,
+
+    /// A directory of partial templates to register, named by their path within the directory.
+    pub partials_dir: Option<PathBuf>,
+
+    /// A directory of Rhai scripts to register as template helpers, named by their file name.
+    pub helpers_dir: Option<PathBuf>,
+
+    /// Whether to write a runtime script for history, saving and loading, which pages can include.
+    #[serde(default)]
+    pub runtime: bool,
+
+    /// The name the runtime script keeps history and saves under, so they survive changes to the
+    /// script. Defaults to the title of the story.
+    pub storage_key: Option<String>,
+
+    /// A seed for the random choices of the runtime script, so playthroughs can be repeated when
+    /// testing. Without one, choices are truly random.
+    pub random_seed: Option<u64>,
 
     /// Additional files to copy to output directory.
     pub additional_files: Option<Vec<PathBuf>>,
 
     /// The path of the script file to use.
     pub script: PathBuf,
+
+    /// Translations of the adventure by language code, each with its own script. When given, each
+    /// is built into its own subdirectory of the output directory.
+    pub locales: Option<BTreeMap<String, Locale>>,
+
+    /// The indentation style of the script. If unset, it is detected from the script itself.
+    pub indent: Option<Indentation>,
+
+    /// Information about the adventure as a whole, passed to every page.
+    #[serde(default)]
+    pub story: Story,
+}
+
+impl Config {
+    /// The name the runtime script keeps history and saves under, which is the `storage-key` if
+    /// given, or else the title of the story.
+    pub fn storage_key(&self) -> &str {
+        self.storage_key
+            .as_deref()
+            .or(self.story.title.as_deref())
+            .unwrap_or("adventure")
+    }
+}
+
+fn default_extension() -> String {
+    "html".to_owned()
+}
+
+/// The kind of files an adventure is built into.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// HTML pages, rendered from the templates.
+    #[default]
+    Html,
+
+    /// Gemtext pages, for Gemini capsules. Templates aren't used.
+    Gemtext,
+}
+
+/// A translation of the adventure, as given in the `[locales]` table of the config.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Locale {
+    /// The path of the translated script file. It must have the same pages, in the same order, with
+    /// the same links as the main script.
+    pub script: PathBuf,
+}
+
+/// Adventure-wide information, as given in the `[story]` table of the config.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Story {
+    /// The title of the whole adventure.
+    pub title: Option<String>,
+
+    /// The author of the adventure.
+    pub author: Option<String>,
+
+    /// The language the adventure is written in, such as `"en"`.
+    pub language: Option<String>,
+
+    /// A short description of the adventure.
+    pub description: Option<String>,
+
+    /// The version of the adventure.
+    pub version: Option<String>,
+}
+
+/// The indentation style as written in the config file, either 2 or 4 spaces, or `"tab"`.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum IndentValue {
+    Spaces(usize),
+    Name(String),
+}
+
+impl TryFrom<IndentValue> for Indentation {
+    type Error = String;
+
+    fn try_from(value: IndentValue) -> Result<Self, Self::Error> {
+        match value {
+            IndentValue::Spaces(count @ (2 | 4)) => Ok(Self::Spaces(count)),
+            IndentValue::Spaces(count) => Err(format!(
+                "unknown indentation of {count} spaces, expected 2, 4 or \"tab\""
+            )),
+            IndentValue::Name(name) if name == "tab" => Ok(Self::Tab),
+            IndentValue::Name(name) => Err(format!(
+                "unknown indentation {name:?}, expected 2, 4 or \"tab\""
+            )),
+        }
+    }
+}
+
+impl From<Indentation> for IndentValue {
+    fn from(indentation: Indentation) -> Self {
+        match indentation {
+            Indentation::Spaces(count) => Self::Spaces(count),
+            Indentation::Tab => Self::Name("tab".to_owned()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Indentation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = IndentValue::deserialize(deserializer)?;
+        Self::try_from(value).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Indentation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        IndentValue::from(*self).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndentValue;
+    use crate::script::parse::Indentation;
+
+    #[test]
+    fn only_accepts_known_indentation() {
+        assert_eq!(
+            Ok(Indentation::Spaces(2)),
+            IndentValue::Spaces(2).try_into()
+        );
+        assert_eq!(
+            Ok(Indentation::Tab),
+            IndentValue::Name("tab".to_owned()).try_into()
+        );
+
+        for value in [
+            IndentValue::Spaces(0),
+            IndentValue::Spaces(3),
+            IndentValue::Spaces(8),
+        ] {
+            assert!(Indentation::try_from(value).is_err());
+        }
+    }
 }
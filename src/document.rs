@@ -0,0 +1,254 @@
+//! Readable documents of a whole adventure, for reviewing and diffing stories without rendering
+//! any HTML.
+
+use crate::adventure::{Adventure, PageLink, Story};
+use std::fmt::Write;
+
+/// Escape the brackets of link text, which would otherwise end the link early.
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+/// The anchor of a page, which is its identifier with every character but letters, digits and a
+/// few marks percent-encoded, so it can be used both as an attribute and within a link.
+fn anchor(identifier: &str) -> String {
+    let mut anchor = String::with_capacity(identifier.len());
+    for byte in identifier.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_./~".contains(&byte) {
+            anchor.push(char::from(byte));
+        } else {
+            let _ = write!(anchor, "%{byte:02X}");
+        }
+    }
+    anchor
+}
+
+/// End the line of a choice, noting why it's disabled if it is.
+fn disabled_reason(output: &mut String, link: &PageLink) {
+    match link.disabled {
+        Some(reason) => {
+            let _ = writeln!(output, " (disabled: {reason})");
+        }
+        None => output.push('\n'),
+    }
+}
+
+/// Write an adventure as a Markdown document, with a heading for each page. Each heading has an
+/// anchor named after the page identifier, which choices link to.
+pub fn markdown(adventure: &Adventure, story: &Story) -> String {
+    let mut output = String::new();
+
+    if let Some(title) = &story.info.title {
+        let _ = writeln!(output, "# {title}");
+    }
+
+    for page in &adventure.pages {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        let _ = write!(
+            output,
+            "<a id=\"{}\"></a>\n\n## {}\n",
+            anchor(page.identifier),
+            page.title
+        );
+
+        for paragraph in page.paragraphs {
+            let _ = write!(output, "\n{paragraph}\n");
+        }
+
+        if !page.links.is_empty() || !page.random.is_empty() {
+            output.push('\n');
+        }
+        for link in &page.links {
+            // Page indices start from 1.
+            let target = anchor(adventure.pages[link.index - 1].identifier);
+            let _ = write!(output, "- [{}](#{target})", escape_link_text(link.text));
+            disabled_reason(&mut output, link);
+        }
+        for choice in &page.random {
+            let total = choice.total_weight();
+            let _ = writeln!(output, "- {}", choice.text);
+
+            for outcome in &choice.outcomes {
+                let _ = writeln!(
+                    output,
+                    "  - [{}](#{}) ({} in {total})",
+                    escape_link_text(outcome.text),
+                    anchor(outcome.identifier),
+                    outcome.weight
+                );
+            }
+        }
+    }
+
+    output
+}
+
+/// Write an adventure as a plain text document, with a heading for each page, followed by its
+/// identifier. Choices are listed with the identifier of the page they lead to.
+pub fn text(adventure: &Adventure, story: &Story) -> String {
+    let mut output = String::new();
+
+    if let Some(title) = &story.info.title {
+        let _ = writeln!(output, "{title}\n{}", "=".repeat(title.chars().count()));
+    }
+
+    for page in &adventure.pages {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        let heading = format!("{} [{}]", page.title, page.identifier);
+        let _ = writeln!(output, "{heading}\n{}", "-".repeat(heading.chars().count()));
+
+        for paragraph in page.paragraphs {
+            let _ = write!(output, "\n{paragraph}\n");
+        }
+
+        if !page.links.is_empty() || !page.random.is_empty() {
+            output.push('\n');
+        }
+        for link in &page.links {
+            let target = adventure.pages[link.index - 1].identifier;
+            let _ = write!(output, "* {} -> {target}", link.text);
+            disabled_reason(&mut output, link);
+        }
+        for choice in &page.random {
+            let total = choice.total_weight();
+            let _ = writeln!(output, "* {}", choice.text);
+
+            for outcome in &choice.outcomes {
+                let _ = writeln!(
+                    output,
+                    "  - {} -> {} ({} in {total})",
+                    outcome.text, outcome.identifier, outcome.weight
+                );
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown, text};
+    use crate::{
+        adventure::{Adventure, Story},
+        config,
+        script::Script,
+    };
+
+    const SCRIPT: &str = "page start
+    title
+        Start
+    text
+        You wake up.
+
+        It's *cold*.
+    link cellar
+        Go [down].
+    link cellar disabled \"Too dark\"
+        Jump down.
+    random
+        Roll the dice.
+        link cellar weight 2
+            Fall down.
+        link start
+            Stay put.
+
+page cellar
+    title
+        The Cellar
+";
+
+    #[test]
+    fn can_write_markdown() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = config::Story {
+            title: Some("The House".to_owned()),
+            ..config::Story::default()
+        };
+        let story = Story::new(&info, &adventure, 0);
+
+        let expected = "# The House
+
+<a id=\"start\"></a>
+
+## Start
+
+You wake up.
+
+It's *cold*.
+
+- [Go \\[down\\].](#cellar)
+- [Jump down.](#cellar) (disabled: Too dark)
+- Roll the dice.
+  - [Fall down.](#cellar) (2 in 3)
+  - [Stay put.](#start) (1 in 3)
+
+<a id=\"cellar\"></a>
+
+## The Cellar
+";
+
+        assert_eq!(expected, markdown(&adventure, &story));
+    }
+
+    #[test]
+    fn can_encode_anchors() {
+        let script = Script::new(
+            "page the \"start\"
+    title
+        Start
+    link dark cellar
+        Go down.
+
+page dark cellar
+    title
+        The Cellar
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = config::Story::default();
+        let story = Story::new(&info, &adventure, 0);
+
+        let output = markdown(&adventure, &story);
+
+        assert!(output.starts_with("<a id=\"the%20%22start%22\"></a>"));
+        assert!(output.contains("- [Go down.](#dark%20cellar)\n"));
+        assert!(output.contains("<a id=\"dark%20cellar\"></a>"));
+    }
+
+    #[test]
+    fn can_write_text() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = config::Story::default();
+        let story = Story::new(&info, &adventure, 0);
+
+        let expected = "Start [start]
+-------------
+
+You wake up.
+
+It's *cold*.
+
+* Go [down]. -> cellar
+* Jump down. -> cellar (disabled: Too dark)
+* Roll the dice.
+  - Fall down. -> cellar (2 in 3)
+  - Stay put. -> start (1 in 3)
+
+The Cellar [cellar]
+-------------------
+";
+
+        assert_eq!(expected, text(&adventure, &story));
+    }
+}
@@ -0,0 +1,316 @@
+//! EPUB 3 output, for reading adventures as gamebooks on e-readers.
+
+use crate::adventure::{Adventure, Page, Story};
+use std::{
+    fmt::Write as _,
+    io::{Cursor, Write},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE: &str = "h1 { text-align: center; }
+h2 { font-size: 1em; font-style: italic; text-align: center; }
+ul.choices { list-style: none; padding: 0; }
+ul.choices li { margin: 0.5em 0; }
+";
+
+/// Escape text for XML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// The name of the file holding a section.
+fn section_file(number: usize) -> String {
+    format!("section-{number}.xhtml")
+}
+
+/// Start an XHTML document with the given title.
+fn xhtml_head(output: &mut String, lang: &str, title: &str) {
+    let lang = escape(lang);
+    let _ = write!(
+        output,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+<head>
+  <meta charset="UTF-8"/>
+  <title>{}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+"#,
+        escape(title)
+    );
+}
+
+/// Write a page as a numbered section, with its links as links to other sections. Disabled links
+/// are listed without a link, followed by the reason they're disabled. Random choices list a link to
+/// each outcome, with its chances, for the reader to pick between.
+fn section(numbers: &[usize], page: &Page, lang: &str) -> String {
+    // Page indices start from 1.
+    let number = numbers[page.index - 1];
+
+    let mut output = String::new();
+    xhtml_head(&mut output, lang, &number.to_string());
+
+    let _ = write!(
+        output,
+        "<section id=\"section-{number}\" epub:type=\"chapter\">\n<h1>{number}</h1>\n<h2>{}</h2>\n",
+        escape(page.title)
+    );
+
+    for paragraph in page.paragraphs {
+        let _ = writeln!(output, "<p>{}</p>", escape(paragraph));
+    }
+
+    if !page.links.is_empty() || !page.random.is_empty() {
+        output.push_str("<ul class=\"choices\">\n");
+        for link in &page.links {
+            if let Some(reason) = link.disabled {
+                let text = format!("{} ({reason})", link.text);
+                let _ = writeln!(output, "<li>{}</li>", escape(&text));
+                continue;
+            }
+
+            let target = numbers[link.index - 1];
+            let _ = writeln!(
+                output,
+                "<li><a href=\"{}\">{}</a></li>",
+                section_file(target),
+                escape(link.text)
+            );
+        }
+        for choice in &page.random {
+            let total = choice.total_weight();
+            let _ = writeln!(output, "<li>{}\n<ul>", escape(choice.text));
+
+            for outcome in &choice.outcomes {
+                let target = numbers[outcome.index - 1];
+                let _ = writeln!(
+                    output,
+                    "<li><a href=\"{}\">{}</a> ({} in {total})</li>",
+                    section_file(target),
+                    escape(outcome.text),
+                    outcome.weight
+                );
+            }
+            output.push_str("</ul>\n</li>\n");
+        }
+        output.push_str("</ul>\n");
+    }
+
+    output.push_str("</section>\n</body>\n</html>\n");
+    output
+}
+
+/// Write the navigation document, listing every section in order.
+fn nav(numbers: &[usize], lang: &str, title: &str) -> String {
+    let mut output = String::new();
+    xhtml_head(&mut output, lang, title);
+
+    output.push_str("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Sections</h1>\n<ol>\n");
+    for number in 1..=numbers.len() {
+        let _ = writeln!(
+            output,
+            "<li><a href=\"{}\">{number}</a></li>",
+            section_file(number)
+        );
+    }
+    output.push_str("</ol>\n</nav>\n</body>\n</html>\n");
+
+    output
+}
+
+/// Write the package document, describing the book and listing its files in reading order.
+fn package(numbers: &[usize], story: &Story, lang: &str, title: &str) -> String {
+    let mut output = String::new();
+
+    let _ = write!(
+        output,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id" xml:lang="{}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="id">urn:uuid:{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>
+"#,
+        escape(lang),
+        story.identifier,
+        escape(title),
+        escape(lang)
+    );
+
+    if let Some(author) = &story.info.author {
+        let _ = writeln!(output, "    <dc:creator>{}</dc:creator>", escape(author));
+    }
+    if let Some(description) = &story.info.description {
+        let _ = writeln!(
+            output,
+            "    <dc:description>{}</dc:description>",
+            escape(description)
+        );
+    }
+
+    let _ = write!(
+        output,
+        r#"    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+"#,
+        story.timestamp
+    );
+
+    for number in 1..=numbers.len() {
+        let _ = writeln!(
+            output,
+            r#"    <item id="section-{number}" href="{}" media-type="application/xhtml+xml"/>"#,
+            section_file(number)
+        );
+    }
+
+    output.push_str("  </manifest>\n  <spine>\n");
+    for number in 1..=numbers.len() {
+        let _ = writeln!(output, r#"    <itemref idref="section-{number}"/>"#);
+    }
+    output.push_str("  </spine>\n</package>\n");
+
+    output
+}
+
+/// Write an adventure as an EPUB 3 book, with each page as a section numbered as given (by page
+/// index) and links between sections as hyperlinks. Sections are written in numbered order.
+pub fn export(
+    adventure: &Adventure,
+    story: &Story,
+    numbers: &[usize],
+) -> zip::result::ZipResult<Vec<u8>> {
+    let lang = story.info.language.as_deref().unwrap_or("en");
+    let title = story.info.title.as_deref().unwrap_or("Untitled");
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must come first, uncompressed, so the file can be identified by its first bytes.
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package(numbers, story, lang, title).as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav(numbers, lang, title).as_bytes())?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLE.as_bytes())?;
+
+    for page in &adventure.pages {
+        let number = numbers[page.index - 1];
+        zip.start_file(format!("OEBPS/{}", section_file(number)), deflated)?;
+        zip.write_all(section(numbers, page, lang).as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export;
+    use crate::{
+        adventure::{Adventure, Story},
+        config,
+        script::Script,
+    };
+    use std::io::{Cursor, Read};
+    use zip::{CompressionMethod, ZipArchive};
+
+    #[test]
+    fn can_write_epub() {
+        let script = Script::new(
+            "page start
+    title
+        Start
+    text
+        You & me.
+    link end
+        Finish.
+    link end disabled \"Not yet\"
+        Skip to the end.
+    random
+        Flip a coin.
+        link end weight 3
+            Heads.
+        link start
+            Tails.
+
+page end
+    title
+        The End
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = config::Story {
+            title: Some("The <Book>".to_owned()),
+            ..config::Story::default()
+        };
+        let story = Story::new(&info, &adventure, 0);
+
+        let bytes = export(&adventure, &story, &[1, 2]).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        let read = |archive: &mut ZipArchive<_>, name: &str| {
+            let mut contents = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            contents
+        };
+
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!("mimetype", mimetype.name());
+        assert_eq!(CompressionMethod::Stored, mimetype.compression());
+        drop(mimetype);
+
+        let package = read(&mut archive, "OEBPS/content.opf");
+        assert!(package.contains("<dc:title>The &lt;Book&gt;</dc:title>"));
+        assert!(package.contains("<dc:language>en</dc:language>"));
+        assert!(package.contains(r#"<itemref idref="section-2"/>"#));
+
+        let start = read(&mut archive, "OEBPS/section-1.xhtml");
+        assert!(start.contains("<p>You &amp; me.</p>"));
+        assert!(start.contains(r#"<a href="section-2.xhtml">Finish.</a>"#));
+        assert!(start.contains("<li>Skip to the end. (Not yet)</li>"));
+        assert!(start.contains(
+            "<li>Flip a coin.\n<ul>\n<li><a href=\"section-2.xhtml\">Heads.</a> (3 in 4)</li>"
+        ));
+    }
+}
@@ -7,20 +7,54 @@ pub enum Error {
     /// Failed to parse the config file.
     ParseConfig(toml::de::Error),
 
-    /// Failed to read the script file to a string.
-    ReadScript(std::io::Error),
+    /// Failed to read a script file to a string.
+    ReadScript(std::path::PathBuf, std::io::Error),
 
-    /// Failed to parse the script.
-    ParseScript(Vec<(usize, crate::script::parse::Error)>),
+    /// Failed to parse a script.
+    ParseScript(
+        std::path::PathBuf,
+        Vec<(usize, crate::script::parse::Error)>,
+    ),
+
+    /// Failed to read a translation catalog to a string.
+    ReadCatalog(std::path::PathBuf, std::io::Error),
+
+    /// Failed to parse a translation catalog.
+    ParseCatalog(std::path::PathBuf, usize, crate::catalog::po::Error),
 
     /// Failed to create output directory.
     Directory(std::io::Error),
 
+    /// HTML output was asked for without a template.
+    MissingTemplate,
+
     /// Failed to read the template file to a string.
     ReadTemplate(std::io::Error),
 
-    /// A template was not valid.
-    BadTemplate(handlebars::TemplateError),
+    /// Failed to read one of the named template files to a string.
+    ReadNamedTemplate(std::path::PathBuf, std::io::Error),
+
+    /// Failed to read a partial template file or directory.
+    ReadPartial(std::path::PathBuf, std::io::Error),
+
+    /// A template was not valid. The handlebars error is boxed, as it's much larger than the rest.
+    BadTemplate(Box<handlebars::TemplateError>),
+
+    /// Failed to read a helper script file or directory.
+    ReadHelper(std::path::PathBuf, std::io::Error),
+
+    /// A helper script was not valid. Handlebars doesn't export its script error type, so this is
+    /// the error message.
+    BadHelper(std::path::PathBuf, String),
+
+    /// A helper script was named after a built-in helper.
+    BuiltInHelper(std::path::PathBuf, String),
+
+    /// Sections were asked to be shuffled for a format other than EPUB.
+    ShuffleFormat,
+
+    /// A seed was given without any sections to shuffle.
+    SeedFormat,
 
     /// Failed to generate an adventure with the given config.
     Adventure(Vec<crate::adventure::error::Error>),
@@ -37,17 +71,46 @@ impl std::fmt::Display for Error {
         match self {
             Error::ReadConfig(e) => write!(f, "Failed to read config file: {e}"),
             Error::ParseConfig(e) => write!(f, "Failed to parse config file: {e}"),
-            Error::ReadScript(e) => write!(f, "Failed to read script file: {e}"),
-            Error::ParseScript(errors) => {
-                writeln!(f, "Failed to parse script file:")?;
+            Error::ReadScript(path, e) => write!(f, "Failed to read script file {path:?}: {e}"),
+            Error::ParseScript(path, errors) => {
+                writeln!(f, "Failed to parse script file {path:?}:")?;
                 for (line, error) in errors {
                     writeln!(f, "Line {}: {}", line + 1, error)?;
                 }
                 Ok(())
             }
+            Error::ReadCatalog(path, e) => write!(f, "Failed to read catalog {path:?}: {e}"),
+            Error::ParseCatalog(path, line, e) => {
+                write!(
+                    f,
+                    "Failed to parse catalog {path:?}: Line {}: {e}",
+                    line + 1
+                )
+            }
             Error::Directory(e) => write!(f, "Failed to create output directory: {e}"),
+            Error::MissingTemplate => write!(f, "A template is required for HTML output."),
             Error::ReadTemplate(e) => write!(f, "Failed to read template file: {e}"),
+            Error::ReadNamedTemplate(path, e) => {
+                write!(f, "Failed to read template file {path:?}: {e}")
+            }
+            Error::ReadPartial(path, e) => write!(f, "Failed to read partial {path:?}: {e}"),
             Error::BadTemplate(e) => write!(f, "Failed parse template file: {e}"),
+            Error::ReadHelper(path, e) => write!(f, "Failed to read helper {path:?}: {e}"),
+            Error::BadHelper(path, e) => write!(f, "Failed to parse helper {path:?}: {e}"),
+            Error::BuiltInHelper(path, name) => {
+                write!(
+                    f,
+                    "The helper {path:?} is named {name:?}, which is a built-in helper."
+                )
+            }
+            Error::ShuffleFormat => write!(
+                f,
+                "Only EPUB sections can be shuffled, printed gamebooks always are."
+            ),
+            Error::SeedFormat => write!(
+                f,
+                "A seed only applies to shuffled sections, of gamebooks or EPUB with `--shuffle`."
+            ),
             Error::Adventure(errors) => {
                 writeln!(f, "Failed to generate adventure from config:")?;
                 for error in errors {
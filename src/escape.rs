@@ -0,0 +1,88 @@
+//! Escape functions for templates, so they can produce formats other than HTML.
+
+use serde::{Deserialize, Serialize};
+
+/// How text from the script is escaped when written by a template.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Escape {
+    /// Escape for HTML, as handlebars does by default.
+    #[default]
+    Html,
+
+    /// Write text as it is.
+    None,
+
+    /// Escape for the inside of a JSON string.
+    Json,
+
+    /// Escape the special characters of LaTeX.
+    Latex,
+}
+
+impl Escape {
+    /// Get the function which escapes text this way.
+    pub fn function(self) -> fn(&str) -> String {
+        match self {
+            Self::Html => handlebars::html_escape,
+            Self::None => handlebars::no_escape,
+            Self::Json => json,
+            Self::Latex => latex,
+        }
+    }
+}
+
+/// Escape text for the inside of a JSON string, without the surrounding quotes.
+fn json(text: &str) -> String {
+    let quoted = serde_json::to_string(text).expect("strings can always be written as JSON");
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'));
+    inner.unwrap_or_default().to_owned()
+}
+
+/// Escape the characters which have a special meaning in LaTeX.
+fn latex(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => output.push_str("\\textbackslash{}"),
+            '~' => output.push_str("\\textasciitilde{}"),
+            '^' => output.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                output.push('\\');
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Escape;
+
+    #[test]
+    fn can_escape() {
+        const TEXT: &str = "\"Fish\" & <chips>\\50% {off}~";
+        const EXAMPLES: &[(Escape, &str)] = &[
+            (
+                Escape::Html,
+                "&quot;Fish&quot; &amp; &lt;chips&gt;\\50% {off}~",
+            ),
+            (Escape::None, TEXT),
+            (Escape::Json, "\\\"Fish\\\" & <chips>\\\\50% {off}~"),
+            (
+                Escape::Latex,
+                "\"Fish\" \\& <chips>\\textbackslash{}50\\% \\{off\\}\\textasciitilde{}",
+            ),
+        ];
+
+        for (escape, expected) in EXAMPLES {
+            assert_eq!(*expected, escape.function()(TEXT));
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Printable gamebooks, where pages are numbered sections and links say which section to turn to.
+
+use crate::adventure::{Adventure, Page, Story};
+use handlebars::html_escape;
+use std::fmt::Write;
+
+/// Styles for printing, keeping each section together where possible.
+const STYLE: &str = "@page { size: A5; margin: 2cm; @bottom-center { content: counter(page); } }
+body { font-family: serif; line-height: 1.4; }
+header { break-after: page; page-break-after: always; text-align: center; }
+section { break-inside: avoid; page-break-inside: avoid; margin-bottom: 2em; }
+h2 { text-align: center; break-after: avoid; page-break-after: avoid; }
+h3 { font-size: 1em; font-style: italic; text-align: center; }
+ul.choices { list-style: none; padding: 0; }
+a { color: inherit; text-decoration: none; }
+";
+
+/// Get the pages of an adventure in order of their section numbers.
+fn in_section_order<'a>(adventure: &'a Adventure, numbers: &[usize]) -> Vec<&'a Page<'a>> {
+    let mut pages: Vec<_> = adventure.pages.iter().collect();
+    // Page indices start from 1.
+    pages.sort_by_key(|page| numbers[page.index - 1]);
+    pages
+}
+
+/// The text of a link, without the full stop it might end with, since the instruction follows.
+fn choice_text(text: &str) -> &str {
+    text.trim_end().trim_end_matches('.')
+}
+
+/// Write an adventure as a single print-ready HTML document, with a section for each page,
+/// numbered as given (by page index).
+pub fn html(adventure: &Adventure, story: &Story, numbers: &[usize]) -> String {
+    let title = story.info.title.as_deref().unwrap_or("Untitled");
+    let lang = story.info.language.as_deref().unwrap_or("en");
+    let mut output = String::new();
+
+    let _ = write!(
+        output,
+        "<!DOCTYPE html>
+<html lang=\"{}\">
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>
+{STYLE}</style>
+</head>
+<body>
+<header>
+<h1>{}</h1>
+",
+        html_escape(lang),
+        html_escape(title),
+        html_escape(title)
+    );
+
+    if let Some(author) = &story.info.author {
+        let _ = writeln!(output, "<p>{}</p>", html_escape(author));
+    }
+    output.push_str("</header>\n");
+
+    for page in in_section_order(adventure, numbers) {
+        let number = numbers[page.index - 1];
+
+        let _ = write!(
+            output,
+            "<section id=\"section-{number}\">\n<h2>{number}</h2>\n<h3>{}</h3>\n",
+            html_escape(page.title)
+        );
+
+        for paragraph in page.paragraphs {
+            let _ = writeln!(output, "<p>{}</p>", html_escape(paragraph));
+        }
+
+        if !page.links.is_empty() || !page.random.is_empty() {
+            output.push_str("<ul class=\"choices\">\n");
+            for link in &page.links {
+                // Disabled links can't be followed, so they give their reason instead of a section.
+                if let Some(reason) = link.disabled {
+                    let text = format!("{} ({reason})", choice_text(link.text));
+                    let _ = writeln!(output, "<li>{}</li>", html_escape(&text));
+                    continue;
+                }
+
+                let target = numbers[link.index - 1];
+                let _ = writeln!(
+                    output,
+                    "<li>{} &mdash; turn to <a href=\"#section-{target}\">{target}</a>.</li>",
+                    html_escape(choice_text(link.text))
+                );
+            }
+            // Readers pick the outcome of a random choice themselves, such as with a die.
+            for choice in &page.random {
+                let total = choice.total_weight();
+                let _ = writeln!(output, "<li>{}\n<ul>", html_escape(choice.text));
+
+                for outcome in &choice.outcomes {
+                    let target = numbers[outcome.index - 1];
+                    let _ = writeln!(
+                        output,
+                        "<li>{} ({} in {total}) &mdash; turn to <a href=\"#section-{target}\">{target}</a>.</li>",
+                        html_escape(choice_text(outcome.text)),
+                        outcome.weight
+                    );
+                }
+                output.push_str("</ul>\n</li>\n");
+            }
+            output.push_str("</ul>\n");
+        }
+
+        output.push_str("</section>\n");
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Write an adventure as a plain text manuscript, with a section for each page, numbered as given
+/// (by page index).
+pub fn text(adventure: &Adventure, story: &Story, numbers: &[usize]) -> String {
+    let mut output = String::new();
+
+    if let Some(title) = &story.info.title {
+        let _ = writeln!(output, "{}", title.to_uppercase());
+    }
+    if let Some(author) = &story.info.author {
+        let _ = writeln!(output, "by {author}");
+    }
+
+    for page in in_section_order(adventure, numbers) {
+        if !output.is_empty() {
+            output.push_str("\n\n");
+        }
+
+        let number = numbers[page.index - 1];
+        let _ = writeln!(output, "{number}. {}", page.title);
+
+        for paragraph in page.paragraphs {
+            let _ = write!(output, "\n{paragraph}\n");
+        }
+
+        if !page.links.is_empty() || !page.random.is_empty() {
+            output.push('\n');
+        }
+        for link in &page.links {
+            if let Some(reason) = link.disabled {
+                let _ = writeln!(output, "{} ({reason}).", choice_text(link.text));
+                continue;
+            }
+
+            let target = numbers[link.index - 1];
+            let _ = writeln!(output, "{} - turn to {target}.", choice_text(link.text));
+        }
+        for choice in &page.random {
+            let total = choice.total_weight();
+            let _ = writeln!(output, "{}", choice.text);
+
+            for outcome in &choice.outcomes {
+                let target = numbers[outcome.index - 1];
+                let _ = writeln!(
+                    output,
+                    "  {} ({} in {total}) - turn to {target}.",
+                    choice_text(outcome.text),
+                    outcome.weight
+                );
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html, text};
+    use crate::{
+        adventure::{Adventure, Story},
+        config,
+        script::Script,
+    };
+
+    const SCRIPT: &str = "page start
+    title
+        Start
+    text
+        You wake up.
+    link cellar
+        Go down.
+    link attic
+        Go up!
+    link attic disabled \"The door is locked\"
+        Open the door.
+    random
+        Roll a die.
+        link cellar weight 5
+            Slip.
+        link attic
+            Climb.
+
+page attic
+    title
+        The Attic
+    text
+        Dusty & dark.
+
+page cellar
+    title
+        The Cellar
+";
+
+    fn info() -> config::Story {
+        config::Story {
+            title: Some("The House".to_owned()),
+            author: Some("A. Writer".to_owned()),
+            ..config::Story::default()
+        }
+    }
+
+    #[test]
+    fn can_write_manuscript() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = info();
+        let story = Story::new(&info, &adventure, 0);
+
+        let expected = "THE HOUSE
+by A. Writer
+
+
+1. Start
+
+You wake up.
+
+Go down - turn to 2.
+Go up! - turn to 3.
+Open the door (The door is locked).
+Roll a die.
+  Slip (5 in 6) - turn to 2.
+  Climb (1 in 6) - turn to 3.
+
+
+2. The Cellar
+
+
+3. The Attic
+
+Dusty & dark.
+";
+
+        assert_eq!(expected, text(&adventure, &story, &[1, 3, 2]));
+    }
+
+    #[test]
+    fn can_write_html() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = info();
+        let story = Story::new(&info, &adventure, 0);
+
+        let output = html(&adventure, &story, &[1, 3, 2]);
+
+        assert!(output.contains("<h1>The House</h1>\n<p>A. Writer</p>"));
+        assert!(output.contains("<li>Go down &mdash; turn to <a href=\"#section-2\">2</a>.</li>"));
+        assert!(output.contains("<li>Open the door (The door is locked)</li>"));
+        assert!(
+            output.contains("<li>Slip (5 in 6) &mdash; turn to <a href=\"#section-2\">2</a>.</li>")
+        );
+        assert!(output.contains("<p>Dusty &amp; dark.</p>"));
+
+        let cellar = output.find("<h3>The Cellar</h3>").unwrap();
+        let attic = output.find("<h3>The Attic</h3>").unwrap();
+        assert!(cellar < attic);
+    }
+}
@@ -0,0 +1,164 @@
+//! Gemtext output, for publishing adventures as Gemini capsules.
+
+use crate::adventure::{Adventure, Page};
+use std::fmt::Write;
+
+/// The extension of gemtext files. Pages are named the same way as HTML pages, such as `1.gmi`.
+pub const EXTENSION: &str = "gmi";
+
+/// Stop a line of text from being read as a heading, link, list item, quote or preformatted toggle.
+/// Gemtext has no escapes, but only the start of a line is significant, so a leading space will do.
+fn text_line(text: &str) -> String {
+    let significant = ["#", "=>", "*", ">", "```"];
+
+    if significant.iter().any(|prefix| text.starts_with(prefix)) {
+        format!(" {text}")
+    } else {
+        text.to_owned()
+    }
+}
+
+/// Write a page as a gemtext document, with its title as a heading and its links as link lines.
+/// Gemtext has no inline markup, so text is written as it is. Disabled links are written as text,
+/// followed by the reason they're disabled. Capsules can't roll dice, so random choices are written
+/// as text followed by a link to each outcome, with its chances. Links are relative to the file of
+/// the page, which may be in the directory of its chapter.
+pub fn page(adventure: &Adventure, page: &Page, chapter_directories: bool) -> String {
+    let root = page.root(chapter_directories);
+    let file_name = |index: usize| {
+        // Page indices start from 1.
+        let target = &adventure.pages[index - 1];
+        format!("{root}{}", target.file_name(EXTENSION, chapter_directories))
+    };
+
+    let mut output = String::new();
+    let _ = writeln!(output, "# {}", page.title);
+
+    for paragraph in page.paragraphs {
+        let _ = write!(output, "\n{}\n", text_line(paragraph));
+    }
+
+    if !page.links.is_empty() || !page.random.is_empty() {
+        output.push('\n');
+    }
+    for link in &page.links {
+        if let Some(reason) = link.disabled {
+            let text = format!("{} ({reason})", link.text);
+            let _ = writeln!(output, "{}", text_line(&text));
+            continue;
+        }
+
+        let _ = writeln!(output, "=> {} {}", file_name(link.index), link.text);
+    }
+    for choice in &page.random {
+        let total = choice.total_weight();
+        let _ = writeln!(output, "{}", text_line(choice.text));
+
+        for outcome in &choice.outcomes {
+            let _ = writeln!(
+                output,
+                "=> {} {} ({} in {total})",
+                file_name(outcome.index),
+                outcome.text,
+                outcome.weight
+            );
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::page;
+    use crate::{adventure::Adventure, script::Script};
+
+    #[test]
+    fn can_write_page() {
+        let script = Script::new(
+            "page start
+    title
+        Fish & \"Chips\"
+    text
+        It's <cold>.
+
+        * Not a list.
+
+        => Not a link.
+    link end
+        Finish & leave.
+    link end disabled \"It's too dark\"
+        Read the sign.
+    random
+        Roll the dice.
+        link end weight 2
+            Win.
+        link start
+            Lose.
+
+page end
+    title
+        The End
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        let expected = "# Fish & \"Chips\"
+
+It's <cold>.
+
+ * Not a list.
+
+ => Not a link.
+
+=> 2.gmi Finish & leave.
+Read the sign. (It's too dark)
+Roll the dice.
+=> 2.gmi Win. (2 in 3)
+=> 1.gmi Lose. (1 in 3)
+";
+
+        assert_eq!(expected, page(&adventure, &adventure.pages[0], false));
+        assert_eq!("# The End\n", page(&adventure, &adventure.pages[1], false));
+    }
+
+    #[test]
+    fn can_link_between_chapter_directories() {
+        let script = Script::new(
+            "page start
+    title
+        Start
+    link chapter2/cave
+        Go in.
+
+chapter chapter2
+    title
+        Into the Dark
+    page cave
+        title
+            Cave
+        link tunnel
+            Onwards.
+        link start
+            Back out.
+    page tunnel
+        title
+            Tunnel
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        assert_eq!(
+            "# Start\n\n=> chapter2/2.gmi Go in.\n",
+            page(&adventure, &adventure.pages[0], true)
+        );
+        assert_eq!(
+            "# Cave\n\n=> ../chapter2/3.gmi Onwards.\n=> ../1.gmi Back out.\n",
+            page(&adventure, &adventure.pages[1], true)
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{adventure::Adventure, error::Error};
+use handlebars::{
+    handlebars_helper, Context, Handlebars, Helper, HelperDef, HelperResult, JsonValue, Output,
+    RenderContext, RenderError, ScopedJson,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// The names of the built-in helpers, which helper scripts can't take.
+const BUILT_IN: [&str; 7] = [
+    "page_url",
+    "page_title",
+    "link_to",
+    "is_ending",
+    "pluralize",
+    "word_count",
+    "markdown",
+];
+
+/// The names of the helpers which handlebars registers itself. Handlebars can't be asked which
+/// helpers it has, so these follow the version in use.
+const STANDARD: [&str; 17] = [
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len",
+];
+
+/// What the helpers need to know about a single page of the adventure.
+struct PageInfo {
+    index: usize,
+
+    /// The path of the page from the output directory.
+    url: String,
+    title: String,
+    is_ending: bool,
+}
+
+/// Information about every page of the adventure by identifier, shared between helpers.
+#[derive(Clone)]
+struct Pages(Arc<HashMap<String, PageInfo>>);
+
+impl Pages {
+    fn new(adventure: &Adventure, extension: &str, chapter_directories: bool) -> Self {
+        let pages = adventure
+            .pages
+            .iter()
+            .map(|page| {
+                let info = PageInfo {
+                    index: page.index,
+                    url: page.file_name(extension, chapter_directories),
+                    title: page.title.to_owned(),
+                    is_ending: page.links.is_empty() && page.random.is_empty(),
+                };
+                (page.identifier.to_owned(), info)
+            })
+            .collect();
+
+        Self(Arc::new(pages))
+    }
+
+    /// Find the page identified by the given parameter of a helper, which is either an identifier
+    /// or the index of a page.
+    fn param(&self, h: &Helper, index: usize) -> Result<&PageInfo, RenderError> {
+        let number = h.param(index).and_then(|param| param.value().as_u64());
+        if let Some(number) = number {
+            return self
+                .0
+                .values()
+                .find(|page| page.index as u64 == number)
+                .ok_or_else(|| {
+                    RenderError::new(format!(
+                        "The {} helper refers to page {number}, but it does not exist.",
+                        h.name()
+                    ))
+                });
+        }
+
+        let identifier = string_param(h, index)?;
+
+        self.0.get(identifier).ok_or_else(|| {
+            RenderError::new(format!(
+                "The {} helper refers to page {identifier:?}, but it does not exist.",
+                h.name()
+            ))
+        })
+    }
+
+    /// Find the page identified by the given parameter of a helper, or the page being rendered if
+    /// there is no such parameter.
+    fn param_or_current(
+        &self,
+        h: &Helper,
+        ctx: &Context,
+        index: usize,
+    ) -> Result<&PageInfo, RenderError> {
+        if h.param(index).is_some() {
+            return self.param(h, index);
+        }
+
+        ctx.data()
+            .get("identifier")
+            .and_then(JsonValue::as_str)
+            .and_then(|identifier| self.0.get(identifier))
+            .ok_or_else(|| RenderError::new(format!("The {} helper requires a page.", h.name())))
+    }
+}
+
+/// Make the URL of a page relative to the page being rendered, which may be in the directory of a
+/// chapter.
+fn relative_url(ctx: &Context, page: &PageInfo) -> String {
+    let root = ctx.data().get("root").and_then(JsonValue::as_str);
+    format!("{}{}", root.unwrap_or_default(), page.url)
+}
+
+/// Get a string parameter of a helper.
+fn string_param<'a>(h: &'a Helper, index: usize) -> Result<&'a str, RenderError> {
+    h.param(index)
+        .and_then(|param| param.value().as_str())
+        .ok_or_else(|| {
+            RenderError::new(format!(
+                "The {} helper requires a string as parameter {}.",
+                h.name(),
+                index + 1
+            ))
+        })
+}
+
+/// Get the name of the file a page is written to, such as `{{page_url "start"}}`.
+struct PageUrl(Pages);
+
+impl HelperDef for PageUrl {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let page = self.0.param(h, 0)?;
+        Ok(JsonValue::String(relative_url(ctx, page)).into())
+    }
+}
+
+/// Get the title of a page, such as `{{page_title "start"}}`.
+struct PageTitle(Pages);
+
+impl HelperDef for PageTitle {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let page = self.0.param(h, 0)?;
+        Ok(JsonValue::String(page.title.clone()).into())
+    }
+}
+
+/// Write a link to a page, such as `{{link_to "start" "Start again"}}`. The text of the link is the
+/// title of the page, unless given. The link is always an HTML `<a>` element, whatever the escape
+/// mode, though its URL and text are escaped as configured.
+struct LinkTo(Pages);
+
+impl HelperDef for LinkTo {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let page = self.0.param(h, 0)?;
+        let text = match h.param(1) {
+            Some(_) => string_param(h, 1)?,
+            None => &page.title,
+        };
+
+        let escape = r.get_escape_fn();
+        out.write(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape(&relative_url(ctx, page)),
+            escape(text)
+        ))?;
+
+        Ok(())
+    }
+}
+
+/// Check whether a page has no links, such as `{{#if (is_ending)}}`. This checks the page being
+/// rendered, unless a page is given.
+struct IsEnding(Pages);
+
+impl HelperDef for IsEnding {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let page = self.0.param_or_current(h, ctx, 0)?;
+        Ok(JsonValue::Bool(page.is_ending).into())
+    }
+}
+
+/// Choose between the singular and plural form of a word, such as
+/// `{{pluralize count "coin" "coins"}}`. The plural defaults to the singular with an added "s".
+struct Pluralize;
+
+impl HelperDef for Pluralize {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let count = h
+            .param(0)
+            .and_then(|param| param.value().as_f64())
+            .ok_or_else(|| RenderError::new("The pluralize helper requires a number."))?;
+        let singular = string_param(h, 1)?;
+
+        let word = if count == 1.0 {
+            singular.to_owned()
+        } else if h.param(2).is_some() {
+            string_param(h, 2)?.to_owned()
+        } else {
+            format!("{singular}s")
+        };
+
+        Ok(JsonValue::String(word).into())
+    }
+}
+
+// Count the words in a string, or an array of strings, such as `{{word_count paragraphs}}`.
+handlebars_helper!(word_count: |value: Json| {
+    match value {
+        JsonValue::String(string) => string.split_whitespace().count(),
+        JsonValue::Array(array) => array
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .map(|string| string.split_whitespace().count())
+            .sum(),
+        _ => 0,
+    }
+});
+
+/// Render a string as Markdown, such as `{{markdown this}}`.
+struct Markdown;
+
+impl HelperDef for Markdown {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let source = string_param(h, 0)?;
+
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(source));
+        out.write(&html)?;
+
+        Ok(())
+    }
+}
+
+/// Register the built-in helpers for templates, which know about the pages of the given adventure,
+/// and that they are written with the given file extension.
+pub fn register(
+    handlebars: &mut Handlebars,
+    adventure: &Adventure,
+    extension: &str,
+    chapter_directories: bool,
+) {
+    let pages = Pages::new(adventure, extension, chapter_directories);
+
+    // Helper scripts can't take these names, so nothing is replaced here.
+    handlebars.register_helper("page_url", Box::new(PageUrl(pages.clone())));
+    handlebars.register_helper("page_title", Box::new(PageTitle(pages.clone())));
+    handlebars.register_helper("link_to", Box::new(LinkTo(pages.clone())));
+    handlebars.register_helper("is_ending", Box::new(IsEnding(pages)));
+    handlebars.register_helper("pluralize", Box::new(Pluralize));
+    handlebars.register_helper("word_count", Box::new(word_count));
+    handlebars.register_helper("markdown", Box::new(Markdown));
+}
+
+/// Register each Rhai script (with a `.rhai` extension) in a directory as a helper, named by the
+/// file name of the script, so `helpers/shout.rhai` becomes `shout`. Scripts named after a built-in
+/// helper are an error, since either the script would replace a standard helper, or one of ours
+/// would replace the script.
+pub fn register_scripts(handlebars: &mut Handlebars, directory: &Path) -> Result<(), Error> {
+    let mut entries = std::fs::read_dir(directory)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| Error::ReadHelper(directory.to_owned(), e))?;
+    entries.sort();
+
+    for path in entries {
+        let name = match path.file_stem() {
+            Some(name) if path.extension().is_some_and(|e| e == "rhai") => name.to_string_lossy(),
+            _ => continue,
+        };
+
+        if BUILT_IN.contains(&name.as_ref()) || STANDARD.contains(&name.as_ref()) {
+            return Err(Error::BuiltInHelper(path.clone(), name.into_owned()));
+        }
+
+        let script =
+            std::fs::read_to_string(&path).map_err(|e| Error::ReadHelper(path.clone(), e))?;
+        handlebars
+            .register_script_helper(&name, &script)
+            .map_err(|e| Error::BadHelper(path.clone(), e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register, register_scripts};
+    use crate::{adventure::Adventure, error::Error, script::Script};
+    use handlebars::{Handlebars, JsonValue};
+
+    const SCRIPT: &str = "page start
+    title
+        The <Start>
+    text
+        One two three.
+        Four five.
+    link end
+        Finish.
+
+page end
+    title
+        The End
+";
+
+    fn render(template: &str, identifier: &str) -> String {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let page = adventure
+            .pages
+            .iter()
+            .find(|page| page.identifier == identifier)
+            .unwrap();
+
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &adventure, "html", false);
+        handlebars.render_template(template, page).unwrap()
+    }
+
+    #[test]
+    fn can_refer_to_pages() {
+        const EXAMPLES: &[(&str, &str)] = &[
+            ("{{page_url \"end\"}}", "2.html"),
+            ("{{page_title \"start\"}}", "The &lt;Start&gt;"),
+            ("{{link_to \"end\"}}", "<a href=\"2.html\">The End</a>"),
+            (
+                "{{link_to \"start\" \"<Again>\"}}",
+                "<a href=\"1.html\">&lt;Again&gt;</a>",
+            ),
+        ];
+
+        for (template, expected) in EXAMPLES {
+            assert_eq!(*expected, render(template, "start"));
+        }
+    }
+
+    #[test]
+    fn can_check_for_endings() {
+        let template = "{{#if (is_ending)}}yes{{else}}no{{/if}}";
+        assert_eq!("no", render(template, "start"));
+        assert_eq!("yes", render(template, "end"));
+
+        let template = "{{#if (is_ending \"end\")}}yes{{else}}no{{/if}}";
+        assert_eq!("yes", render(template, "start"));
+    }
+
+    #[test]
+    fn report_unknown_pages() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &adventure, "html", false);
+
+        let result = handlebars.render_template("{{page_url \"nowhere\"}}", &JsonValue::Null);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn can_use_other_extensions() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &adventure, "tex", false);
+
+        let result = handlebars.render_template("{{page_url \"end\"}}", &JsonValue::Null);
+        assert_eq!("2.tex", result.unwrap());
+    }
+
+    #[test]
+    fn can_link_between_chapter_directories() {
+        let script = Script::new(
+            "page start
+    title
+        Start
+
+chapter chapter2
+    page cave
+        title
+            The Cave
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        let mut handlebars = Handlebars::new();
+        register(&mut handlebars, &adventure, "html", true);
+
+        let context = serde_json::json!({ "identifier": "chapter2/cave", "root": "../" });
+        let template = "{{page_url \"start\"}} {{page_url 2}} {{link_to \"chapter2/cave\"}}";
+        assert_eq!(
+            "../1.html ../chapter2/2.html <a href=\"../chapter2/2.html\">The Cave</a>",
+            handlebars.render_template(template, &context).unwrap()
+        );
+    }
+
+    #[test]
+    fn can_pluralize() {
+        const EXAMPLES: &[(&str, &str)] = &[
+            ("{{pluralize 1 \"coin\"}}", "coin"),
+            ("{{pluralize 0 \"coin\"}}", "coins"),
+            ("{{pluralize 2 \"coin\"}}", "coins"),
+            ("{{pluralize 3 \"mouse\" \"mice\"}}", "mice"),
+        ];
+
+        for (template, expected) in EXAMPLES {
+            assert_eq!(*expected, render(template, "start"));
+        }
+    }
+
+    #[test]
+    fn can_count_words() {
+        assert_eq!("5", render("{{word_count paragraphs}}", "start"));
+        assert_eq!("2", render("{{word_count title}}", "start"));
+    }
+
+    #[test]
+    fn can_render_markdown() {
+        assert_eq!(
+            "<p>Some <em>emphasis</em></p>\n",
+            render("{{markdown \"Some *emphasis*\"}}", "start")
+        );
+    }
+
+    #[test]
+    fn can_register_scripts() {
+        let directory =
+            std::env::temp_dir().join(format!("make-adventure-helpers-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("shout.rhai"), "params[0].to_upper() + \"!\"").unwrap();
+        std::fs::write(directory.join("notes.txt"), "Not a script.").unwrap();
+
+        let mut handlebars = Handlebars::new();
+        register_scripts(&mut handlebars, &directory).unwrap();
+        let shouted = handlebars
+            .render_template("{{shout \"hello\"}}", &JsonValue::Null)
+            .unwrap();
+
+        std::fs::write(directory.join("each.rhai"), "params[0]").unwrap();
+        let standard = register_scripts(&mut Handlebars::new(), &directory);
+
+        std::fs::remove_file(directory.join("each.rhai")).unwrap();
+        std::fs::write(directory.join("link_to.rhai"), "params[0]").unwrap();
+        let ours = register_scripts(&mut Handlebars::new(), &directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!("HELLO!", shouted);
+        assert!(matches!(standard, Err(Error::BuiltInHelper(_, name)) if name == "each"));
+        assert!(matches!(ours, Err(Error::BuiltInHelper(_, name)) if name == "link_to"));
+    }
+}
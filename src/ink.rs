@@ -0,0 +1,398 @@
+//! Export of scripts to Ink, the narrative scripting language by Inkle.
+
+use crate::script::{Condition, Page, Script};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+/// Names which Ink gives a meaning of its own, so can't be used for knots.
+const RESERVED: &[&str] = &[
+    "CONST", "DONE", "END", "EXTERNAL", "INCLUDE", "LIST", "VAR", "and", "else", "false",
+    "function", "mod", "not", "or", "return", "temp", "true",
+];
+
+/// Something to be aware of after exporting to Ink.
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    /// The identifier of a page made the same knot name as another, so was given a different one.
+    Renamed { page: String, knot: String },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::Renamed { page, knot } => write!(
+                f,
+                "Page {page:?} has the same knot name as another page, so is now {knot:?}."
+            ),
+        }
+    }
+}
+
+/// Turn a page identifier into a valid Ink name, made of ASCII letters, digits and underscores, not
+/// starting with a digit.
+fn sanitize(identifier: &str) -> String {
+    let mut name: String = identifier
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    if RESERVED.contains(&name.as_str()) {
+        name.push('_');
+    }
+
+    name
+}
+
+/// Make a name unique among those already used, by numbering it, and mark it as used.
+fn unique(base: &str, used: &mut HashSet<String>) -> String {
+    let mut name = base.to_owned();
+    let mut count = 1;
+
+    while used.contains(&name) {
+        count += 1;
+        name = format!("{base}_{count}");
+    }
+
+    used.insert(name.clone());
+    name
+}
+
+/// Escape the characters of some text which Ink would otherwise read as markup. Characters only
+/// need escaping where they're meaningful, so most text is left as it is.
+fn escape(text: &str, in_choice: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut start = true;
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+
+        let special = match c {
+            '\\' | '#' | '{' | '}' | '|' => true,
+            '[' | ']' => in_choice,
+            '/' => matches!(next, Some('/' | '*')),
+            '-' if next == Some('>') => true,
+            '<' => matches!(next, Some('>' | '-')),
+            '*' | '+' | '-' | '=' | '~' => start,
+            _ => false,
+        };
+
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        start = false;
+    }
+
+    escaped
+}
+
+/// Write a tag, such as `# title: The Cellar`.
+fn write_tag(output: &mut String, key: &str, value: &str) {
+    let _ = writeln!(output, "# {}: {}", escape(key, false), escape(value, false));
+}
+
+/// Write a script as an Ink story, with a knot for each page. Links become choices which divert to
+/// the knot of their page, and pages without links end the story. Titles, templates and metadata
+/// are kept as tags.
+pub fn export(script: &Script) -> (String, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    let mut knots = HashMap::new();
+    let mut used = HashSet::new();
+
+    for page in &script.pages {
+        let base = sanitize(&page.identifier);
+        let knot = unique(&base, &mut used);
+
+        if knot != base {
+            warnings.push(Warning::Renamed {
+                page: page.identifier.to_string(),
+                knot: knot.clone(),
+            });
+        }
+
+        knots.insert(&*page.identifier, knot);
+    }
+
+    // The flags of `hidden-unless` links become variables, for the game to set.
+    let mut flags = BTreeMap::new();
+    for page in &script.pages {
+        for flag in page.links.iter().filter_map(|link| link.hidden_unless) {
+            if !flags.contains_key(flag) {
+                let variable = unique(&format!("flag_{}", sanitize(flag)), &mut used);
+                flags.insert(flag, variable);
+            }
+        }
+    }
+
+    let knot = |identifier: &str| {
+        knots
+            .get(identifier)
+            .cloned()
+            .unwrap_or_else(|| sanitize(identifier))
+    };
+
+    // Pages within a chapter can link to the others without naming the chapter.
+    let target_knot = |page: &Page, target: &str| match page.find(&script.pages, target) {
+        Some(position) => knot(&script.pages[position].identifier),
+        None => knot(target),
+    };
+
+    // Conditions check the read counts of knots, which count the current visit to a knot too.
+    let expression = |page: &Page, condition: &Condition| match condition {
+        Condition::Visited(target) => target_knot(page, target),
+        Condition::NotVisited(target) => format!("not {}", target_knot(page, target)),
+        Condition::FirstVisit => format!("{} == 1", knot(&page.identifier)),
+        Condition::Revisit => format!("{} > 1", knot(&page.identifier)),
+    };
+
+    let mut output = String::new();
+
+    // Tags before any content are global tags of the story.
+    for (key, value) in &script.meta {
+        write_tag(&mut output, key, value);
+    }
+
+    for variable in flags.values() {
+        let _ = writeln!(output, "VAR {variable} = false");
+    }
+
+    match script.pages.first() {
+        Some(page) => {
+            let _ = writeln!(output, "-> {}", knot(&page.identifier));
+        }
+        None => output.push_str("-> END\n"),
+    }
+
+    for page in &script.pages {
+        let _ = write!(output, "\n=== {} ===\n", knot(&page.identifier));
+
+        write_tag(&mut output, "title", page.title);
+        if let Some(template) = page.template {
+            write_tag(&mut output, "template", template);
+        }
+        for (key, value) in &page.meta {
+            write_tag(&mut output, key, value);
+        }
+
+        let conditions = page.paragraph_conditions.iter();
+        for (paragraph, condition) in page.paragraphs.iter().zip(conditions) {
+            let text = escape(paragraph, false);
+            let _ = match condition {
+                Some(condition) => writeln!(output, "{{{}: {text}}}", expression(page, condition)),
+                None => writeln!(output, "{text}"),
+            };
+        }
+
+        // Disabled links can't be taken, so they're written as text, before the choices.
+        for link in &page.links {
+            if let Some(reason) = link.disabled {
+                let text = format!("{} ({reason})", link.text);
+                let _ = writeln!(output, "{}", escape(&text, false));
+            }
+        }
+
+        let links: Vec<_> = page.links.iter().filter(|l| l.disabled.is_none()).collect();
+        if links.is_empty() && page.random.is_empty() {
+            output.push_str("-> END\n");
+        }
+
+        // Links taken once become once-only choices, and those hidden unless a flag is set check
+        // its variable.
+        for link in links {
+            let bullet = if link.once { '*' } else { '+' };
+            let mut checks = String::new();
+            if let Some(condition) = &link.condition {
+                let _ = write!(checks, "{{{}}} ", expression(page, condition));
+            }
+            if let Some(flag) = link.hidden_unless {
+                let _ = write!(checks, "{{{}}} ", flags[flag]);
+            }
+            let _ = writeln!(
+                output,
+                "{bullet} {checks}[{}] -> {}",
+                escape(link.text, true),
+                target_knot(page, &link.target)
+            );
+        }
+
+        // Random choices roll a number up to the total weight, and divert to the first outcome
+        // whose weight, added to those before it, reaches the roll.
+        for choice in &page.random {
+            let check = match &choice.condition {
+                Some(condition) => format!("{{{}}} ", expression(page, condition)),
+                None => String::new(),
+            };
+
+            if let [outcome] = choice.outcomes.as_slice() {
+                let target = target_knot(page, &outcome.target);
+                let text = escape(choice.text, true);
+                let _ = writeln!(output, "+ {check}[{text}] -> {target}");
+                continue;
+            }
+
+            let total: u64 = choice.outcomes.iter().map(|o| u64::from(o.weight)).sum();
+            let _ = writeln!(
+                output,
+                "+ {check}[{}]\n    ~ temp roll = RANDOM(1, {total})\n    {{",
+                escape(choice.text, true)
+            );
+
+            let mut reached = 0;
+            for (position, outcome) in choice.outcomes.iter().enumerate() {
+                let target = target_knot(page, &outcome.target);
+                reached += u64::from(outcome.weight);
+
+                if position + 1 == choice.outcomes.len() {
+                    let _ = writeln!(output, "        - else: -> {target}");
+                } else {
+                    let _ = writeln!(output, "        - roll <= {reached}: -> {target}");
+                }
+            }
+            output.push_str("    }\n");
+        }
+    }
+
+    (output, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, export, sanitize, Warning};
+    use crate::script::Script;
+
+    #[test]
+    fn can_sanitize_names() {
+        const EXAMPLES: &[(&str, &str)] = &[
+            ("start", "start"),
+            ("dungeon-entrance", "dungeon_entrance"),
+            ("2nd-floor", "_2nd_floor"),
+            ("END", "END_"),
+            ("café", "caf_"),
+        ];
+
+        for (identifier, expected) in EXAMPLES {
+            assert_eq!(*expected, sanitize(identifier));
+        }
+    }
+
+    #[test]
+    fn can_escape_text() {
+        const EXAMPLES: &[(&str, bool, &str)] = &[
+            (
+                "Plain text, with - dashes.",
+                false,
+                "Plain text, with - dashes.",
+            ),
+            ("- Not a gather", false, "\\- Not a gather"),
+            (
+                "{x} | # -> <> // /*",
+                false,
+                "\\{x\\} \\| \\# \\-> \\<> \\// \\/*",
+            ),
+            ("[Bracketed]", false, "[Bracketed]"),
+            ("[Bracketed]", true, "\\[Bracketed\\]"),
+        ];
+
+        for (text, in_choice, expected) in EXAMPLES {
+            assert_eq!(*expected, escape(text, *in_choice));
+        }
+    }
+
+    #[test]
+    fn can_export_script() {
+        let script = Script::new(
+            "meta mood calm
+
+page start
+    title
+        The Start
+    template dark
+    text
+        You wake up.
+
+        It's cold.
+    text if not first-visit
+        You're back.
+    link dungeon-entrance
+        Go down.
+    link dungeon_entrance
+        Go [really] down.
+    link dungeon-entrance once hidden-unless rope
+        Abseil down.
+    link dungeon_entrance if not visited(dungeon-entrance)
+        Sneak down.
+    random if visited(dungeon_entrance)
+        Jump.
+        link dungeon-entrance weight 4
+            Land.
+        link start weight 2
+            Bounce.
+
+page dungeon-entrance
+    title
+        The Entrance
+    meta music drip.ogg
+    link start
+        Back up.
+
+page dungeon_entrance
+    title
+        The Other Entrance
+    link start disabled \"The way back is blocked\"
+        Climb back up.
+",
+            None,
+        )
+        .unwrap();
+
+        let (output, warnings) = export(&script);
+
+        let expected = "# mood: calm
+VAR flag_rope = false
+-> start
+
+=== start ===
+# title: The Start
+# template: dark
+You wake up.
+It's cold.
+{start > 1: You're back.}
++ [Go down.] -> dungeon_entrance
++ [Go \\[really\\] down.] -> dungeon_entrance_2
+* {flag_rope} [Abseil down.] -> dungeon_entrance
++ {not dungeon_entrance} [Sneak down.] -> dungeon_entrance_2
++ {dungeon_entrance_2} [Jump.]
+    ~ temp roll = RANDOM(1, 6)
+    {
+        - roll <= 4: -> dungeon_entrance
+        - else: -> start
+    }
+
+=== dungeon_entrance ===
+# title: The Entrance
+# music: drip.ogg
++ [Back up.] -> start
+
+=== dungeon_entrance_2 ===
+# title: The Other Entrance
+Climb back up. (The way back is blocked)
+-> END
+";
+
+        assert_eq!(expected, output);
+        assert_eq!(
+            vec![Warning::Renamed {
+                page: "dungeon_entrance".to_owned(),
+                knot: "dungeon_entrance_2".to_owned()
+            }],
+            warnings
+        );
+    }
+}
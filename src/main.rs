@@ -2,64 +2,207 @@
 
 mod adventure;
 mod args;
+mod catalog;
 mod config;
+mod document;
+mod epub;
 mod error;
+mod escape;
+mod gamebook;
+mod gemtext;
+mod helpers;
+mod ink;
+mod runtime;
 mod script;
+mod sections;
+mod twee;
 
-use crate::{adventure::Adventure, args::Args, config::Config, error::Error};
+use crate::{
+    adventure::{Adventure, PageContext, Story},
+    args::{Args, Command, ExportFormat},
+    config::{Config, Locale, OutputFormat},
+    error::Error,
+};
 use clap::Parser;
 use handlebars::Handlebars;
-use script::Script;
-use std::{path::PathBuf, process::ExitCode};
+use script::{parse::syntax::SyntaxTree, Script};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-/// Read and parse the config file named by the arguments.
-fn get_config(args: &Args) -> Result<Config, Error> {
-    let string = std::fs::read_to_string(&args.config).map_err(Error::ReadConfig)?;
+/// Read and parse the config file.
+fn get_config(path: &Path) -> Result<Config, Error> {
+    let string = std::fs::read_to_string(path).map_err(Error::ReadConfig)?;
     let config = toml::from_str(&string).map_err(Error::ParseConfig)?;
     Ok(config)
 }
 
-fn get_script_source(config: &Config) -> Result<String, Error> {
-    std::fs::read_to_string(&config.script).map_err(Error::ReadScript)
+fn get_script_source(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|e| Error::ReadScript(path.to_owned(), e))
 }
 
-fn get_script(source: &str) -> Result<Script, Error> {
-    Script::new(source).map_err(Error::ParseScript)
+fn get_script<'a>(config: &Config, path: &Path, source: &'a str) -> Result<Script<'a>, Error> {
+    Script::new(source, config.indent).map_err(|e| Error::ParseScript(path.to_owned(), e))
+}
+
+/// Parse a script, keeping its comments and layout.
+fn get_syntax_tree<'a>(
+    config: &Config,
+    path: &Path,
+    source: &'a str,
+) -> Result<SyntaxTree<'a>, Error> {
+    SyntaxTree::parse(source, config.indent).map_err(|e| Error::ParseScript(path.to_owned(), e))
 }
 
 /// Create the output directory (if it does not already exist).
-fn create_output_dir(args: &Args) -> Result<(), Error> {
-    std::fs::create_dir_all(&args.output).map_err(Error::Directory)
+fn create_output_dir(output: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(output).map_err(Error::Directory)
 }
 
 /// Create and configure a handlebars instance from the given config.
-fn create_handlebars(config: &Config) -> Result<Handlebars, Error> {
-    let template = std::fs::read_to_string(&config.template).map_err(Error::ReadTemplate)?;
-
+fn create_handlebars(config: &Config) -> Result<Handlebars<'static>, Error> {
     let mut handlebars = Handlebars::new();
-    handlebars
-        .register_template_string("template", template)
-        .map_err(Error::BadTemplate)?;
+    handlebars.register_escape_fn(config.escape.function());
+
+    if let Some(path) = &config.template {
+        let template = std::fs::read_to_string(path).map_err(Error::ReadTemplate)?;
+        handlebars
+            .register_template_string("template", template)
+            .map_err(|e| Error::BadTemplate(Box::new(e)))?;
+    }
+
+    if let Some(templates) = &config.templates {
+        for (name, path) in templates {
+            let template = std::fs::read_to_string(path)
+                .map_err(|e| Error::ReadNamedTemplate(path.clone(), e))?;
+            handlebars
+                .register_template_string(name, template)
+                .map_err(|e| Error::BadTemplate(Box::new(e)))?;
+        }
+    }
+
+    register_partials(config, &mut handlebars)?;
+    if let Some(directory) = &config.helpers_dir {
+        helpers::register_scripts(&mut handlebars, directory)?;
+    }
 
     Ok(handlebars)
 }
 
+/// Check that every page selects the main template or one named in the config. Partials are
+/// registered as templates too, so the handlebars instance can't be asked.
+fn check_templates(config: &Config, adventure: &Adventure) -> Result<(), Error> {
+    let names: Vec<_> = std::iter::once("template")
+        .chain(
+            config
+                .templates
+                .iter()
+                .flatten()
+                .map(|(name, _)| name.as_str()),
+        )
+        .collect();
+
+    adventure.check_templates(&names).map_err(Error::Adventure)
+}
+
+/// Register the partial templates named by the config. Partials named explicitly take precedence
+/// over those found in the partials directory.
+fn register_partials(config: &Config, handlebars: &mut Handlebars) -> Result<(), Error> {
+    let mut partials = Vec::new();
+
+    if let Some(directory) = &config.partials_dir {
+        find_partials(directory, directory, &mut partials)?;
+    }
+
+    if let Some(named) = &config.partials {
+        partials.extend(
+            named
+                .iter()
+                .map(|(name, path)| (name.clone(), path.clone())),
+        );
+    }
+
+    for (name, path) in partials {
+        let partial = std::fs::read_to_string(&path).map_err(|e| Error::ReadPartial(path, e))?;
+        handlebars
+            .register_partial(&name, partial)
+            .map_err(|e| Error::BadTemplate(Box::new(e)))?;
+    }
+
+    Ok(())
+}
+
+/// Recursively find partial templates in a directory. Each is named by its path relative to the
+/// root directory, without an extension, so `partials/nav/choices.hbs` becomes `nav/choices`.
+fn find_partials(
+    root: &Path,
+    directory: &Path,
+    partials: &mut Vec<(String, PathBuf)>,
+) -> Result<(), Error> {
+    let mut entries = std::fs::read_dir(directory)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| Error::ReadPartial(directory.to_owned(), e))?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_partials(root, &path, partials)?;
+        } else if let Ok(relative) = path.with_extension("").strip_prefix(root) {
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            partials.push((name, path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the time of the build in seconds since the Unix epoch. This can be fixed with the
+/// `SOURCE_DATE_EPOCH` environment variable, for reproducible builds.
+fn build_time() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|time| time.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        })
+}
+
 /// Generate and write a page to the output directory.
 fn generate_page(
-    args: &Args,
+    output: &Path,
     handlebars: &Handlebars,
-    index: usize,
-    page: &adventure::Page,
+    context: &PageContext,
+    file_name: &str,
 ) -> Result<(), Error> {
-    let output = handlebars
-        .render("template", &page)
+    let rendered = handlebars
+        .render(context.page.template.unwrap_or("template"), context)
         .map_err(Error::PageGeneration)?;
 
-    let mut path = PathBuf::from(&args.output);
-    // Indicies offset by 1, since they're read by humans.
-    path.push(format!("{}.html", index + 1));
+    let path = output.join(file_name);
+    // Pages may be written to the directory of their chapter.
+    if let Some(directory) = path.parent() {
+        create_output_dir(directory)?;
+    }
+
+    write_output(path, rendered)
+}
 
-    match std::fs::write(&path, output) {
+/// Write a file to the output directory.
+fn write_output(path: PathBuf, contents: String) -> Result<(), Error> {
+    match std::fs::write(&path, contents) {
         Ok(()) => {
             println!("Wrote {path:?}");
             Ok(())
@@ -69,13 +212,13 @@ fn generate_page(
 }
 
 /// Copy additional files mentioned by the config to the output directory.
-fn copy_additional_files(args: &Args, config: &Config) -> Result<(), Error> {
+fn copy_additional_files(output: &Path, config: &Config) -> Result<(), Error> {
     if let Some(additional_files) = &config.additional_files {
         for file in additional_files {
-            let mut destination = PathBuf::from(&args.output);
-            destination.push(&file);
+            let mut destination = PathBuf::from(output);
+            destination.push(file);
 
-            std::fs::copy(&file, &destination).map_err(|e| Error::WriteOutput(destination, e))?;
+            std::fs::copy(file, &destination).map_err(|e| Error::WriteOutput(destination, e))?;
             println!("Copied {file:?}");
         }
     }
@@ -83,23 +226,292 @@ fn copy_additional_files(args: &Args, config: &Config) -> Result<(), Error> {
     Ok(())
 }
 
-fn run() -> Result<(), Error> {
-    let args = Args::parse();
-    let config = get_config(&args)?;
-    let script_source = get_script_source(&config)?;
-    let script = get_script(&script_source)?;
+/// Build an adventure from a script, writing its pages to the output directory.
+fn build(
+    output: &Path,
+    config: &Config,
+    handlebars: &mut Handlebars,
+    script: &Script,
+    lang: Option<&str>,
+) -> Result<(), Error> {
+    create_output_dir(output)?;
 
-    create_output_dir(&args)?;
+    let adventure = Adventure::new(script).map_err(Error::Adventure)?;
 
-    let handlebars = create_handlebars(&config)?;
+    match config.format {
+        OutputFormat::Html => {
+            if config.template.is_none() {
+                return Err(Error::MissingTemplate);
+            }
 
-    let adventure = Adventure::new(&script).map_err(Error::Adventure)?;
+            let extension = &config.extension;
+            let directories = config.chapter_directories;
+            helpers::register(handlebars, &adventure, extension, directories);
+            check_templates(config, &adventure)?;
+            let story = Story::new(&config.story, &adventure, build_time());
+
+            // Without the runtime, conditional text and links are always shown.
+            let conditional = adventure.pages.iter().find(|page| page.has_conditions());
+            match conditional {
+                Some(page) if !config.runtime => eprintln!(
+                    "Page {:?} has conditions, which are only checked by the runtime script, so \
+                     they'll be ignored unless `runtime` is enabled in the config.",
+                    page.identifier
+                ),
+                _ => {}
+            }
+
+            if config.runtime {
+                let path = output.join(runtime::FILE_NAME);
+                let key = config.storage_key();
+                let seed = config.random_seed;
+                let script = runtime::script(&adventure, key, extension, directories, seed);
+                write_output(path, script)?;
+            }
+
+            for page in &adventure.pages {
+                let root = page.root(directories);
+                let context = PageContext {
+                    page,
+                    story: &story,
+                    lang,
+                    root,
+                    runtime: config.runtime.then(|| runtime::tag(page, root)),
+                };
+                let file_name = page.file_name(extension, directories);
+                generate_page(output, handlebars, &context, &file_name)?;
+            }
+        }
+        OutputFormat::Gemtext => {
+            let directories = config.chapter_directories;
+            for page in &adventure.pages {
+                let path = output.join(page.file_name(gemtext::EXTENSION, directories));
+                // Pages may be written to the directory of their chapter.
+                if let Some(directory) = path.parent() {
+                    create_output_dir(directory)?;
+                }
+                write_output(path, gemtext::page(&adventure, page, directories))?;
+            }
+        }
+    }
+
+    copy_additional_files(output, config)
+}
+
+/// Build the main script into the output directory, and every translation of the adventure into its
+/// own subdirectory of it. All translations are checked against the main script before anything is
+/// built.
+fn build_locales(
+    output: &Path,
+    config: &Config,
+    handlebars: &mut Handlebars,
+    script: &Script,
+    locales: &BTreeMap<String, Locale>,
+) -> Result<(), Error> {
+    let sources = locales
+        .iter()
+        .map(|(lang, locale)| Ok((lang, &locale.script, get_script_source(&locale.script)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut translations = Vec::with_capacity(sources.len());
+    let mut errors = Vec::new();
+
+    for (lang, path, source) in &sources {
+        let translation = get_script(config, path, source)?;
+
+        if let Err(e) = adventure::translation::check(lang, script, &translation) {
+            errors.extend(e);
+        }
+
+        translations.push((lang, translation));
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::Adventure(errors));
+    }
+
+    let lang = config.story.language.as_deref();
+    build(output, config, handlebars, script, lang)?;
+
+    for (lang, translation) in translations {
+        let output = output.join(lang);
+        build(&output, config, handlebars, &translation, Some(lang))?;
+    }
+
+    Ok(())
+}
+
+/// Build the adventure described by the config file to the output directory.
+fn build_all(config_path: &Path, output: &Path) -> Result<(), Error> {
+    let config = get_config(config_path)?;
+    let script_source = get_script_source(&config.script)?;
+    let script = get_script(&config, &config.script, &script_source)?;
+
+    let mut handlebars = create_handlebars(&config)?;
+
+    match &config.locales {
+        Some(locales) => build_locales(output, &config, &mut handlebars, &script, locales),
+        None => {
+            let lang = config.story.language.as_deref();
+            build(output, &config, &mut handlebars, &script, lang)
+        }
+    }
+}
+
+/// Write the translatable text of the script to a PO file.
+fn extract(config_path: &Path, catalog_path: &Path) -> Result<(), Error> {
+    let config = get_config(config_path)?;
+    let source = get_script_source(&config.script)?;
+    get_script(&config, &config.script, &source)?;
+    let tree = get_syntax_tree(&config, &config.script, &source)?;
+
+    let name = config.script.to_string_lossy();
+    let entries = catalog::extract(&tree, &name);
+
+    std::fs::write(catalog_path, catalog::po::write(&entries))
+        .map_err(|e| Error::WriteOutput(catalog_path.to_owned(), e))?;
+    println!("Wrote {catalog_path:?}");
+
+    Ok(())
+}
 
-    for (index, page) in adventure.pages.iter().enumerate() {
-        generate_page(&args, &handlebars, index, page)?;
+/// Write a translated script using the translations of a PO file. Missing, stale and unwritable
+/// translations are reported, but don't stop the script being written.
+fn import(config_path: &Path, catalog_path: &Path, output: &Path) -> Result<(), Error> {
+    let config = get_config(config_path)?;
+    let source = get_script_source(&config.script)?;
+    let script = get_script(&config, &config.script, &source)?;
+    let tree = get_syntax_tree(&config, &config.script, &source)?;
+
+    let catalog_source = std::fs::read_to_string(catalog_path)
+        .map_err(|e| Error::ReadCatalog(catalog_path.to_owned(), e))?;
+    let entries = catalog::po::parse(&catalog_source)
+        .map_err(|(line, e)| Error::ParseCatalog(catalog_path.to_owned(), line, e))?;
+
+    let import = catalog::import(&tree, &entries);
+
+    for context in &import.missing {
+        eprintln!("Missing translation for {context:?}, keeping the original text.");
+    }
+    for context in &import.stale {
+        eprintln!("Stale translation for {context:?}, since the original text has changed.");
     }
+    for context in &import.unwritable {
+        eprintln!(
+            "Translation for {context:?} would be read as a directive or comment, keeping the \
+             original text."
+        );
+    }
+
+    // Make sure the translated text didn't change the structure of the script.
+    let locale = entries
+        .iter()
+        .find(|entry| entry.is_header())
+        .and_then(|header| header.header_field("Language"))
+        .map_or_else(|| catalog_path.to_string_lossy(), Into::into);
+    let translation = get_script(&config, output, &import.script)?;
+    adventure::translation::check(&locale, &script, &translation).map_err(Error::Adventure)?;
+
+    std::fs::write(output, &import.script).map_err(|e| Error::WriteOutput(output.to_owned(), e))?;
+    println!("Wrote {output:?}");
 
-    copy_additional_files(&args, &config)
+    Ok(())
+}
+
+/// Print the warnings of a conversion between formats, returning its output.
+fn report<W: std::fmt::Display>((output, warnings): (String, Vec<W>)) -> String {
+    for warning in &warnings {
+        eprintln!("{warning}");
+    }
+
+    output
+}
+
+/// Export the adventure described by the config file to a single file in another format. Printed
+/// gamebooks always have their sections shuffled with the given seed, and EPUBs only if asked.
+fn export(
+    config_path: &Path,
+    output: &Path,
+    format: ExportFormat,
+    shuffle: bool,
+    seed: Option<u64>,
+) -> Result<(), Error> {
+    let shuffled = match format {
+        ExportFormat::Epub => shuffle,
+        _ if shuffle => return Err(Error::ShuffleFormat),
+        ExportFormat::Gamebook | ExportFormat::Manuscript => true,
+        _ => false,
+    };
+    if seed.is_some() && !shuffled {
+        return Err(Error::SeedFormat);
+    }
+    let seed = seed.unwrap_or_default();
+
+    let config = get_config(config_path)?;
+    let source = get_script_source(&config.script)?;
+    let script = get_script(&config, &config.script, &source)?;
+    let adventure = Adventure::new(&script).map_err(Error::Adventure)?;
+    let story = Story::new(&config.story, &adventure, build_time());
+    let numbers = |shuffle: bool| sections::numbers(adventure.pages.len(), shuffle.then_some(seed));
+
+    let exported = match format {
+        ExportFormat::Twee => report(twee::export(&adventure, &story)).into_bytes(),
+        ExportFormat::Ink => report(ink::export(&script)).into_bytes(),
+        ExportFormat::Epub => epub::export(&adventure, &story, &numbers(shuffle))
+            .map_err(|e| Error::WriteOutput(output.to_owned(), e.into()))?,
+        ExportFormat::Gamebook => gamebook::html(&adventure, &story, &numbers(true)).into_bytes(),
+        ExportFormat::Manuscript => gamebook::text(&adventure, &story, &numbers(true)).into_bytes(),
+        ExportFormat::Markdown => document::markdown(&adventure, &story).into_bytes(),
+        ExportFormat::Text => document::text(&adventure, &story).into_bytes(),
+    };
+
+    std::fs::write(output, exported).map_err(|e| Error::WriteOutput(output.to_owned(), e))?;
+    println!("Wrote {output:?}");
+
+    Ok(())
+}
+
+/// Convert a Twee story into a script. Anything which can't be converted is reported.
+fn import_twee(input: &Path, output: &Path) -> Result<(), Error> {
+    let source = get_script_source(input)?;
+    let script = report(twee::import(&source));
+
+    // Text which happens to look like a directive could still spoil the script.
+    Script::new(&script, None).map_err(|e| Error::ParseScript(output.to_owned(), e))?;
+
+    std::fs::write(output, &script).map_err(|e| Error::WriteOutput(output.to_owned(), e))?;
+    println!("Wrote {output:?}");
+
+    Ok(())
+}
+
+fn run() -> Result<(), Error> {
+    let args = Args::parse();
+
+    match (args.command, args.config, args.output) {
+        (Some(Command::Extract { config, catalog }), ..) => extract(&config, &catalog),
+        (
+            Some(Command::Import {
+                config,
+                catalog,
+                output,
+            }),
+            ..,
+        ) => import(&config, &catalog, &output),
+        (
+            Some(Command::Export {
+                config,
+                output,
+                format,
+                shuffle,
+                seed,
+            }),
+            ..,
+        ) => export(&config, &output, format, shuffle, seed),
+        (Some(Command::ImportTwee { input, output }), ..) => import_twee(&input, &output),
+        (None, Some(config), Some(output)) => build_all(&config, &output),
+        (None, ..) => unreachable!("clap requires a config and output without a subcommand"),
+    }
 }
 
 fn main() -> ExitCode {
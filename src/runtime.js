@@ -0,0 +1,244 @@
+// Save, load and history for adventures made with make-adventure.
+//
+// The path through the adventure is kept in localStorage as a list of page identifiers, which
+// stay the same when pages are reordered, so saves survive changes to the script. Buttons can be
+// added to templates with `data-adventure` attributes, such as:
+//
+//     <button data-adventure="back">Back</button>
+//     <button data-adventure="save" data-slot="1">Save</button>
+//     <button data-adventure="load" data-slot="1">Load</button>
+//     <button data-adventure="restart">Restart</button>
+//
+// The same actions are available to other scripts as `window.adventure`.
+//
+// Random choices are marked with `data-random` attributes, giving the position of the choice
+// within the page, such as `data-random="0"`. Clicking one sends the player to one of its outcomes.
+//
+// Paragraphs and links with conditions, marked with `data-if` attributes such as
+// `data-if="visited(cellar)"`, are hidden unless their condition holds. Links marked with
+// `data-once` are hidden once the player has taken them, and those marked with `data-flag` are
+// hidden unless the flag has been set with `adventure.flag(name)`.
+(function () {
+    "use strict";
+
+    var current = document.currentScript && document.currentScript.dataset.page;
+    // Pages are found relative to the script, since pages may be in the directories of chapters.
+    var base = document.currentScript ? document.currentScript.src : location.href;
+    var prefix = "make-adventure/" + ADVENTURE.storage + "/";
+
+    function read(key) {
+        try {
+            return JSON.parse(localStorage.getItem(prefix + key));
+        } catch (e) {
+            return null;
+        }
+    }
+
+    function write(key, value) {
+        try {
+            localStorage.setItem(prefix + key, JSON.stringify(value));
+        } catch (e) {
+            // Storage may be full or disabled, in which case progress just isn't kept.
+        }
+    }
+
+    // Pages which have since been removed from the script are dropped from the history.
+    function known(history) {
+        return Array.isArray(history)
+            ? history.filter(function (page) { return ADVENTURE.pages.hasOwnProperty(page); })
+            : [];
+    }
+
+    function history() {
+        return known(read("history"));
+    }
+
+    function go(page) {
+        location.href = new URL(ADVENTURE.pages[page], base).href;
+    }
+
+    function back() {
+        var path = history();
+        if (path.length < 2) {
+            return false;
+        }
+
+        path.pop();
+        write("history", path);
+        go(path[path.length - 1]);
+        return true;
+    }
+
+    function flags() {
+        var set = read("flags");
+        return Array.isArray(set) ? set : [];
+    }
+
+    // Set or clear a flag, which decides whether links marked with `data-flag` are shown.
+    function flag(name, on) {
+        var set = flags().filter(function (other) { return other !== name; });
+        if (on !== false) {
+            set.push(name);
+        }
+
+        write("flags", set);
+        update();
+    }
+
+    function save(slot) {
+        var saves = read("saves") || {};
+        saves[slot] = { history: history(), flags: flags(), time: new Date().toISOString() };
+        write("saves", saves);
+    }
+
+    function load(slot) {
+        var saves = read("saves") || {};
+        var path = saves[slot] && known(saves[slot].history);
+        if (!path || path.length === 0) {
+            return false;
+        }
+
+        write("history", path);
+        write("flags", Array.isArray(saves[slot].flags) ? saves[slot].flags : []);
+        go(path[path.length - 1]);
+        return true;
+    }
+
+    function slots() {
+        var saves = read("saves") || {};
+        return Object.keys(saves).map(function (slot) {
+            var path = known(saves[slot].history);
+            return { slot: slot, page: path[path.length - 1], time: saves[slot].time };
+        });
+    }
+
+    // A number from 0 up to 1 for a random choice. With a seed in the config, this depends only on
+    // the seed, the length of the path so far and the position of the choice within the page, so
+    // the same playthrough always rolls the same numbers.
+    function roll(choice) {
+        if (ADVENTURE.seed === null) {
+            return Math.random();
+        }
+
+        // Mulberry32, a small pseudo-random number generator.
+        var t = (ADVENTURE.seed + Math.imul(history().length, 0x9e3779b9) + choice + 0x6d2b79f5) >>> 0;
+        t = Math.imul(t ^ (t >>> 15), t | 1);
+        t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+        return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    }
+
+    // Send the player to one of the outcomes of a random choice on this page, picked by weight.
+    function random(choice) {
+        var outcomes = (ADVENTURE.random[current] || [])[choice];
+        if (!outcomes) {
+            return false;
+        }
+
+        var total = outcomes.reduce(function (sum, outcome) { return sum + outcome[1]; }, 0);
+        var remaining = roll(choice) * total;
+
+        for (var i = 0; i < outcomes.length; i++) {
+            remaining -= outcomes[i][1];
+            if (remaining < 0 || i === outcomes.length - 1) {
+                go(outcomes[i][0]);
+                return true;
+            }
+        }
+    }
+
+    function restart() {
+        write("history", []);
+        write("flags", []);
+        go(ADVENTURE.start);
+    }
+
+    // Whether a condition from the script holds, such as `visited(cellar)` or `not first-visit`.
+    function holds(condition, path) {
+        var negated = condition.indexOf("not ") === 0;
+        var test = negated ? condition.slice(4).trim() : condition;
+        var result = false;
+
+        if (test === "first-visit") {
+            result = path.filter(function (page) { return page === current; }).length <= 1;
+        } else {
+            var match = /^visited\((.+)\)$/.exec(test);
+            result = match !== null && path.indexOf(match[1]) !== -1;
+        }
+
+        return negated ? !result : result;
+    }
+
+    // Whether the player has gone straight from this page to the page a link leads to.
+    function taken(link, path) {
+        var target = Object.keys(ADVENTURE.pages).filter(function (page) {
+            return new URL(ADVENTURE.pages[page], base).href === link.href;
+        })[0];
+
+        for (var i = 1; i < path.length; i++) {
+            if (path[i - 1] === current && path[i] === target) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    // Text and links are marked by templates with `data-if`, `data-once` and `data-flag`
+    // attributes, and are only shown when all of them allow it.
+    function update() {
+        var path = history();
+        var set = flags();
+        var marked = document.querySelectorAll("[data-if], [data-once], [data-flag]");
+
+        Array.prototype.forEach.call(marked, function (element) {
+            var condition = element.getAttribute("data-if");
+            var flagged = element.getAttribute("data-flag");
+
+            element.hidden = (condition !== null && !holds(condition, path))
+                || (element.hasAttribute("data-once") && taken(element, path))
+                || (flagged !== null && set.indexOf(flagged) === -1);
+        });
+    }
+
+    var path = history();
+    if (current && path[path.length - 1] !== current) {
+        path.push(current);
+        write("history", path);
+    }
+
+    update();
+
+    window.adventure = {
+        history: history,
+        back: back,
+        save: save,
+        load: load,
+        slots: slots,
+        random: random,
+        restart: restart,
+        flag: flag,
+        flags: flags,
+    };
+
+    document.addEventListener("click", function (event) {
+        var choice = event.target.closest && event.target.closest("[data-random]");
+        if (choice && random(Number(choice.dataset.random))) {
+            event.preventDefault();
+            return;
+        }
+
+        var target = event.target.closest && event.target.closest("[data-adventure]");
+        if (!target) {
+            return;
+        }
+
+        var slot = target.dataset.slot || "default";
+        switch (target.dataset.adventure) {
+            case "back": back(); break;
+            case "save": save(slot); break;
+            case "load": load(slot); break;
+            case "restart": restart(); break;
+            default: return;
+        }
+        event.preventDefault();
+    });
+})();
@@ -0,0 +1,189 @@
+//! The optional runtime script for HTML output, which keeps the history of the player in
+//! `localStorage`, so they can go back, save and load.
+
+use crate::adventure::{Adventure, Page};
+use handlebars::html_escape;
+use serde_json::{json, Map};
+
+/// The name of the runtime script, written to the output directory.
+pub const FILE_NAME: &str = "adventure.js";
+
+/// The runtime itself, which is the same for every adventure.
+const RUNTIME: &str = include_str!("runtime.js");
+
+/// Make the runtime script for an adventure, preceded by the file of each page by identifier, and
+/// the outcomes of the random choices of each page. Saves are kept under the given storage key,
+/// so different adventures on one site don't mix, and saves survive changes to the script.
+pub fn script(
+    adventure: &Adventure,
+    storage_key: &str,
+    extension: &str,
+    chapter_directories: bool,
+    seed: Option<u64>,
+) -> String {
+    let pages: Map<_, _> = adventure
+        .pages
+        .iter()
+        .map(|page| {
+            let url = page.file_name(extension, chapter_directories);
+            (page.identifier.to_owned(), url.into())
+        })
+        .collect();
+
+    let random: Map<_, _> = adventure
+        .pages
+        .iter()
+        .filter(|page| !page.random.is_empty())
+        .map(|page| {
+            let choices: Vec<_> = page
+                .random
+                .iter()
+                .map(|choice| {
+                    let outcomes = choice.outcomes.iter();
+                    let outcomes: Vec<_> = outcomes
+                        .map(|outcome| json!([outcome.identifier, outcome.weight]))
+                        .collect();
+                    outcomes
+                })
+                .collect();
+            (page.identifier.to_owned(), choices.into())
+        })
+        .collect();
+
+    let info = json!({
+        "storage": storage_key,
+        "start": adventure.pages.first().map(|page| page.identifier),
+        "pages": pages,
+        "random": random,
+        "seed": seed,
+    });
+
+    format!("var ADVENTURE = {info};\n\n{RUNTIME}")
+}
+
+/// Make the tag which includes the runtime in a page, telling it which page it's on. The root is
+/// the path from the page back to the output directory, where the runtime is written.
+pub fn tag(page: &Page, root: &str) -> String {
+    format!(
+        "<script src=\"{}{FILE_NAME}\" data-page=\"{}\" defer></script>",
+        html_escape(root),
+        html_escape(page.identifier)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tag;
+    use crate::{adventure::Adventure, script::Script};
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    const SCRIPT: &str = "page start
+    title
+        Start
+    link end
+        Finish.
+    random
+        Roll the dice.
+        link start weight 5
+            Roll again.
+        link end
+            Win.
+
+page end
+    title
+        The End
+";
+
+    #[test]
+    fn can_make_script() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        let output = super::script(&adventure, "The Dice", "htm", false, Some(7));
+        let expected = "var ADVENTURE = {\"pages\":{\"end\":\"2.htm\",\"start\":\"1.htm\"},\"random\":{\"start\":[[[\"start\",5],[\"end\",1]]]},\"seed\":7,\"start\":\"start\",\"storage\":\"The Dice\"};\n";
+
+        assert!(output.starts_with(expected));
+        assert!(output.contains("window.adventure"));
+    }
+
+    /// Roll the random choice on the start page once with each of a number of seeds, by running the
+    /// runtime in Node with just enough of a browser for it, giving the page each roll leads to.
+    fn roll_with_seeds(runtime: &str, seeds: usize) -> Vec<String> {
+        let harness = format!(
+            r#"var source = {};
+var pages = [];
+for (var seed = 0; seed < {seeds}; seed++) {{
+    var store = {{}};
+    global.localStorage = {{
+        getItem: function (key) {{ return key in store ? store[key] : null; }},
+        setItem: function (key, value) {{ store[key] = value; }},
+    }};
+    global.window = {{}};
+    global.location = {{ href: "http://example.com/1.htm" }};
+    global.document = {{
+        currentScript: {{ dataset: {{ page: "start" }}, src: "http://example.com/adventure.js" }},
+        querySelectorAll: function () {{ return []; }},
+        addEventListener: function () {{}},
+    }};
+    (0, eval)(source);
+    ADVENTURE.seed = seed;
+    window.adventure.random(0);
+    pages.push(location.href);
+}}
+console.log(pages.join("\n"));
+"#,
+            serde_json::to_string(runtime).unwrap()
+        );
+
+        let mut node = Command::new("node")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Node is needed to run the runtime");
+        let mut stdin = node.stdin.take().unwrap();
+        stdin.write_all(harness.as_bytes()).unwrap();
+        drop(stdin);
+
+        let output = node.wait_with_output().unwrap();
+        assert!(output.status.success());
+
+        let output = String::from_utf8(output.stdout).unwrap();
+        output.lines().map(str::to_owned).collect()
+    }
+
+    /// The runtime is JavaScript, so this runs it in Node, which isn't a dependency of the crate.
+    /// Run it with `cargo test -- --ignored` where Node is installed.
+    #[test]
+    #[ignore = "needs Node"]
+    fn can_pick_outcomes_by_weight() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let runtime = super::script(&adventure, "The Dice", "htm", false, None);
+
+        let pages = roll_with_seeds(&runtime, 600);
+
+        // The same seed always gives the same outcome.
+        assert_eq!(pages, roll_with_seeds(&runtime, 600));
+
+        // Rolling again is five times as likely as winning.
+        let again = pages.iter().filter(|page| page.ends_with("/1.htm")).count();
+        let won = pages.iter().filter(|page| page.ends_with("/2.htm")).count();
+        assert_eq!(600, again + won);
+        assert!((450..550).contains(&again), "rolled again {again} times");
+    }
+
+    #[test]
+    fn can_make_tag() {
+        let script = Script::new(SCRIPT, None).unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+
+        assert_eq!(
+            "<script src=\"adventure.js\" data-page=\"end\" defer></script>",
+            tag(&adventure.pages[1], "")
+        );
+    }
+}
@@ -1,19 +1,195 @@
 pub mod parse;
 
+use serde::{Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+/// A condition on the pages a player has visited, which decides whether a paragraph or link is
+/// shown. These are checked by the runtime script as the adventure is played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition<'a> {
+    /// The player has visited the page with the given identifier.
+    Visited(Cow<'a, str>),
+
+    /// The player has not visited the page with the given identifier.
+    NotVisited(Cow<'a, str>),
+
+    /// The player is on this page for the first time.
+    FirstVisit,
+
+    /// The player has been on this page before.
+    Revisit,
+}
+
+impl<'a> Condition<'a> {
+    /// The page the condition depends on, if it's not the current one.
+    pub fn page(&self) -> Option<&str> {
+        match self {
+            Self::Visited(page) | Self::NotVisited(page) => Some(page),
+            Self::FirstVisit | Self::Revisit => None,
+        }
+    }
+
+    /// The same condition, depending on another page instead, if it depends on one at all.
+    pub fn with_page<'b>(&self, page: &'b str) -> Condition<'b> {
+        match self {
+            Self::Visited(_) => Condition::Visited(page.into()),
+            Self::NotVisited(_) => Condition::NotVisited(page.into()),
+            Self::FirstVisit => Condition::FirstVisit,
+            Self::Revisit => Condition::Revisit,
+        }
+    }
+}
+
+impl Display for Condition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Visited(page) => write!(f, "visited({page})"),
+            Self::NotVisited(page) => write!(f, "not visited({page})"),
+            Self::FirstVisit => write!(f, "first-visit"),
+            Self::Revisit => write!(f, "not first-visit"),
+        }
+    }
+}
+
+/// Conditions are given to templates as they're written in the script, such as `visited(cellar)`.
+impl Serialize for Condition<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A link to another page, along with the modifiers which decide whether it's shown and whether it
+/// can be taken.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link<'a> {
+    /// The identifier of the target page.
+    pub target: Cow<'a, str>,
+
+    /// The user-facing text of the link.
+    pub text: &'a str,
+
+    /// The condition on which the link is shown, if any.
+    pub condition: Option<Condition<'a>>,
+
+    /// Whether the link is hidden once the player has taken it.
+    pub once: bool,
+
+    /// A flag which must be set for the link to be shown, if any.
+    pub hidden_unless: Option<&'a str>,
+
+    /// Why the link can't be taken, if it's shown but disabled.
+    pub disabled: Option<&'a str>,
+}
+
+impl<'a> Link<'a> {
+    /// Make a link to a page without any modifiers.
+    pub fn new(target: &'a str, text: &'a str) -> Self {
+        Self {
+            target: target.into(),
+            text,
+            condition: None,
+            once: false,
+            hidden_unless: None,
+            disabled: None,
+        }
+    }
+
+    /// Whether the link has a condition or any other modifier.
+    pub fn is_modified(&self) -> bool {
+        self.condition.is_some()
+            || self.once
+            || self.hidden_unless.is_some()
+            || self.disabled.is_some()
+    }
+}
+
+/// A choice which sends the player to one of several pages at random.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Random<'a> {
+    /// The user-facing text of the choice.
+    pub text: &'a str,
+
+    /// The pages the choice may lead to.
+    pub outcomes: Vec<Outcome<'a>>,
+
+    /// The condition on which the choice is shown, if any.
+    pub condition: Option<Condition<'a>>,
+}
+
+/// One of the pages a random choice may lead to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome<'a> {
+    /// The identifier of the target page.
+    pub target: Cow<'a, str>,
+
+    /// User-facing text describing the outcome, for formats which list every outcome.
+    pub text: &'a str,
+
+    /// How likely the outcome is, relative to the others. Defaults to 1.
+    pub weight: u32,
+}
+
+/// A group of pages, declared with a `chapter` directive.
+#[derive(Debug)]
+pub struct Chapter<'a> {
+    /// The name of the chapter, which qualifies the identifiers of its pages.
+    pub name: &'a str,
+
+    /// The title of the chapter, which is its name unless given.
+    pub title: &'a str,
+}
+
 /// A single page of the adventure.
 #[derive(Debug)]
 pub struct Page<'a> {
-    /// String used to identify page.
-    pub identifier: &'a str,
+    /// String used to identify page. Pages within a chapter are qualified by its name, such as
+    /// `chapter2/cave`, so they're owned.
+    pub identifier: Cow<'a, str>,
+
+    /// The name of the chapter the page is in, if any.
+    pub chapter: Option<&'a str>,
 
     /// The title of the page.
     pub title: &'a str,
 
-    /// The paragraphs of text within the page.
-    pub paragraphs: Vec<&'a str>,
+    /// The paragraphs of text within the page. Paragraphs from snippets with parameters are owned,
+    /// since they don't appear in the script as they are.
+    pub paragraphs: Vec<Cow<'a, str>>,
+
+    /// The condition of each paragraph, if it has one, in the same order as `paragraphs`.
+    pub paragraph_conditions: Vec<Option<Condition<'a>>>,
+
+    /// The links to other pages.
+    pub links: Vec<Link<'a>>,
+
+    /// Choices which lead to a page picked at random.
+    pub random: Vec<Random<'a>>,
+
+    /// Custom key-value pairs passed along to templates.
+    pub meta: Vec<(&'a str, &'a str)>,
+
+    /// The name of the template to render the page with, if not the default.
+    pub template: Option<&'a str>,
 
-    /// The links to other pages, the target page identifier and user-facing text.
-    pub links: Vec<(&'a str, &'a str)>,
+    /// The identifier of the page this one extends, if any. Once the script is parsed, everything
+    /// this page inherits has been merged into it.
+    pub extends: Option<&'a str>,
+}
+
+impl Page<'_> {
+    /// Find the position of the page which an identifier refers to from this page. Pages in the
+    /// same chapter can be referred to without the name of the chapter, so they're tried first.
+    pub fn find(&self, pages: &[Page], identifier: &str) -> Option<usize> {
+        let position =
+            |identifier: &str| pages.iter().position(|page| page.identifier == identifier);
+
+        self.chapter
+            .and_then(|chapter| position(&format!("{chapter}/{identifier}")))
+            .or_else(|| position(identifier))
+    }
 }
 
 /// A configuration of an adventure.
@@ -21,10 +197,19 @@ pub struct Page<'a> {
 pub struct Script<'a> {
     /// The list of all pages of the adventure.
     pub pages: Vec<Page<'a>>,
+
+    /// The chapters of the adventure, in order.
+    pub chapters: Vec<Chapter<'a>>,
+
+    /// Default key-value pairs for every page, which pages may override.
+    pub meta: Vec<(&'a str, &'a str)>,
 }
 
 impl<'a> Script<'a> {
-    pub fn new(string: &'a str) -> Result<Self, Vec<(usize, parse::Error)>> {
-        parse::parse(string)
+    pub fn new(
+        string: &'a str,
+        indentation: Option<parse::Indentation>,
+    ) -> Result<Self, Vec<(usize, parse::Error)>> {
+        parse::parse(string, indentation)
     }
 }
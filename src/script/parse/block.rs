@@ -107,7 +107,7 @@ impl<'a> Block<'a> {
 #[cfg(test)]
 mod tests {
     use super::{
-        super::{line::DirectiveKind, string_to_lines, Error},
+        super::{line::DirectiveKind, string_to_lines, Error, Indentation},
         Block, Line,
     };
 
@@ -156,7 +156,7 @@ mod tests {
         Flee.
 ";
 
-        let expected = vec![Block::internal(
+        let expected = [Block::internal(
             0,
             DirectiveKind::Page,
             Some("dungeon-entrance"),
@@ -194,7 +194,7 @@ mod tests {
             ],
         )];
 
-        let lines = string_to_lines(input).unwrap();
+        let lines = string_to_lines(input, None).unwrap();
         let actual = Block::parse(lines).unwrap();
 
         assert_eq!(expected.len(), actual.len());
@@ -216,9 +216,9 @@ page second-try
                 But not this!
 ";
 
-        let expected = vec![(1, 1, 2), (7, 2, 4)];
+        let expected = [(1, 1, 2), (7, 2, 4)];
 
-        let lines = string_to_lines(input).unwrap();
+        let lines = string_to_lines(input, Some(Indentation::Spaces(4))).unwrap();
         let actual = Block::parse(lines).unwrap_err();
 
         assert_eq!(expected.len(), actual.len());
@@ -0,0 +1,157 @@
+use std::borrow::Cow;
+
+use super::{block::Block, line::DirectiveKind, page::PageBlock, snippet::Snippets, Error, Result};
+use crate::script::{Chapter, Page};
+
+impl<'a> Chapter<'a> {
+    /// Parse a chapter declaration, such as `chapter chapter2`, along with its title and pages. The
+    /// identifiers of its pages are qualified by the name of the chapter, such as `chapter2/cave`,
+    /// and are returned with the lines they're declared on.
+    pub fn parse(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+        snippets: &Snippets<'a>,
+    ) -> Result<(Self, Vec<(usize, Page<'a>)>)> {
+        let mut errors = Vec::new();
+
+        let name = argument.unwrap_or_else(|| {
+            errors.push(Error::missing_argument(line, DirectiveKind::Chapter));
+            "{unnamed}"
+        });
+
+        // The name is used as the name of a directory, so it must stay within the output directory.
+        let invalid = |c: char| c == '/' || c == '\\' || c.is_whitespace();
+        if name.contains(invalid) || name == "." || name == ".." {
+            errors.push(Error::invalid_chapter_name(line, name));
+        }
+
+        let mut titles = Vec::with_capacity(1);
+        let mut pages = Vec::new();
+
+        for child in children {
+            match PageBlock::parse(child, snippets) {
+                Ok((_, PageBlock::Title(title))) => titles.push(title),
+                Ok((n, PageBlock::Page(mut page))) => {
+                    page.identifier = Cow::Owned(format!("{name}/{}", page.identifier));
+                    page.chapter = Some(name);
+                    pages.push((n, page));
+                }
+                Ok((n, _)) => errors.push(Error::non_page_chapter_block(n, name)),
+                Err(e) => errors.extend(e),
+            }
+        }
+
+        let title = match titles.as_slice() {
+            [] => name,
+            [t] => t,
+            [first, ..] => {
+                errors.push(Error::excessive_chapter_titles(line, name));
+                first
+            }
+        };
+
+        if errors.is_empty() {
+            Ok((Self { name, title }, pages))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::{parse::Error, Script};
+
+    #[test]
+    fn can_parse_chapters() {
+        let script = Script::new(
+            "page start
+    title
+        Start
+    link chapter2/cave
+        Skip ahead.
+
+chapter chapter2
+    title
+        Into the Dark
+    page cave
+        title
+            The Cave
+        link start
+            Go back.
+
+chapter chapter3
+    page cave
+        title
+            Another Cave
+",
+            None,
+        )
+        .unwrap();
+
+        let chapters: Vec<_> = script.chapters.iter().map(|c| (c.name, c.title)).collect();
+        assert_eq!(
+            vec![("chapter2", "Into the Dark"), ("chapter3", "chapter3")],
+            chapters
+        );
+
+        let pages: Vec<_> = script
+            .pages
+            .iter()
+            .map(|page| (page.identifier.as_ref(), page.chapter))
+            .collect();
+        assert_eq!(
+            vec![
+                ("start", None),
+                ("chapter2/cave", Some("chapter2")),
+                ("chapter3/cave", Some("chapter3"))
+            ],
+            pages
+        );
+    }
+
+    #[test]
+    fn report_invalid_chapter_names() {
+        for name in ["../../x", "a/b", "a\\b", "..", ".", "part one"] {
+            let input = format!("chapter {name}\n    page cave\n        title\n            Cave\n");
+            let errors = Script::new(&input, None).unwrap_err();
+
+            assert_eq!(1, errors.len(), "{name}");
+            assert!(matches!(
+                &errors[0],
+                (0, Error::InvalidChapterName { name: found }) if found == name
+            ));
+        }
+    }
+
+    #[test]
+    fn report_chapter_errors() {
+        let errors = Script::new(
+            "chapter a
+    title
+        A
+    title
+        Also A
+    meta mood calm
+
+chapter b
+
+chapter b
+
+chapter c
+    chapter d
+",
+            None,
+        )
+        .unwrap_err();
+
+        let lines: Vec<_> = errors.iter().map(|(line, _)| *line).collect();
+        assert_eq!(vec![5, 0, 9, 12], lines);
+        assert!(matches!(
+            &errors[1].1,
+            Error::ExcessiveChapterTitles { chapter } if chapter == "a"
+        ));
+        assert!(matches!(&errors[2].1, Error::DuplicateChapter { name } if name == "b"));
+    }
+}
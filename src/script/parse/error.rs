@@ -1,19 +1,115 @@
-use super::line::DirectiveKind;
+use super::line::{DirectiveKind, Indentation};
 
 #[derive(Debug)]
 pub enum Error {
-    InvalidIndentation { count: usize },
-    UnexpectedIndenation { expected: usize, found: usize },
-    UnexpectedArgument { block: DirectiveKind },
-    ExcessiveChildCount { block: DirectiveKind },
-    UnexpectedChildDirective { block: DirectiveKind },
-    MissingArgument { block: DirectiveKind },
-    MissingText { block: DirectiveKind },
-    PageMissingTitle { page: String },
-    ExcessivePageTitles { page: String },
+    InvalidIndentation {
+        count: usize,
+        width: usize,
+    },
+    MixedIndentation {
+        expected: Indentation,
+    },
+    UnknownIndentation {
+        count: usize,
+    },
+    UnexpectedIndenation {
+        expected: usize,
+        found: usize,
+    },
+    UnexpectedArgument {
+        block: DirectiveKind,
+    },
+    ExcessiveChildCount {
+        block: DirectiveKind,
+    },
+    UnexpectedChildDirective {
+        block: DirectiveKind,
+    },
+    MissingArgument {
+        block: DirectiveKind,
+    },
+    MissingText {
+        block: DirectiveKind,
+    },
+    UnexpectedChildren {
+        block: DirectiveKind,
+    },
+    MissingMetaValue {
+        key: String,
+    },
+    DuplicateMetaKey {
+        key: String,
+    },
+    InvalidCondition {
+        condition: String,
+    },
+    InvalidWeight {
+        weight: String,
+    },
+    InvalidLinkModifier {
+        modifier: String,
+    },
+    ConditionalOutcome,
+    MissingOutcomes,
+    PageMissingTitle {
+        page: String,
+    },
+    ExcessivePageTitles {
+        page: String,
+    },
+    ExcessivePageTemplates {
+        page: String,
+    },
     UnexpectedText,
-    NestedPage { parent: String, child: String },
+    NestedPage {
+        parent: String,
+        child: String,
+    },
     NonPageTopLevelBlock,
+    NonPageChapterBlock {
+        chapter: String,
+    },
+    InvalidChapterName {
+        name: String,
+    },
+    ExcessiveChapterTitles {
+        chapter: String,
+    },
+    DuplicateChapter {
+        name: String,
+    },
+    MisplacedDirective {
+        block: DirectiveKind,
+    },
+    DuplicateSnippet {
+        name: String,
+    },
+    UnknownSnippet {
+        name: String,
+    },
+    RecursiveSnippet {
+        name: String,
+    },
+    ExcessiveSnippetExpansion {
+        name: String,
+    },
+    MissingSnippetArgument {
+        snippet: String,
+        parameter: String,
+    },
+    UnknownSnippetParameter {
+        snippet: String,
+        parameter: String,
+    },
+    UnknownParentPage {
+        page: String,
+        parent: String,
+    },
+    CyclicInheritance {
+        page: String,
+        parent: String,
+        parent_line: usize,
+    },
 }
 
 impl Error {
@@ -49,6 +145,51 @@ impl Error {
         )
     }
 
+    pub fn non_page_chapter_block(line: usize, chapter: &str) -> (usize, Self) {
+        (
+            line,
+            Self::NonPageChapterBlock {
+                chapter: chapter.to_owned(),
+            },
+        )
+    }
+
+    pub fn invalid_chapter_name(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::InvalidChapterName {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn excessive_chapter_titles(line: usize, chapter: &str) -> (usize, Self) {
+        (
+            line,
+            Self::ExcessiveChapterTitles {
+                chapter: chapter.to_owned(),
+            },
+        )
+    }
+
+    pub fn duplicate_chapter(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::DuplicateChapter {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn excessive_page_templates(line: usize, page: &str) -> (usize, Self) {
+        (
+            line,
+            Self::ExcessivePageTemplates {
+                page: page.to_owned(),
+            },
+        )
+    }
+
     pub fn unexpected_argument(line: usize, block: DirectiveKind) -> (usize, Self) {
         (line, Self::UnexpectedArgument { block })
     }
@@ -57,6 +198,141 @@ impl Error {
         (line, Self::MissingText { block })
     }
 
+    pub fn unexpected_children(line: usize, block: DirectiveKind) -> (usize, Self) {
+        (line, Self::UnexpectedChildren { block })
+    }
+
+    pub fn missing_meta_value(line: usize, key: &str) -> (usize, Self) {
+        (
+            line,
+            Self::MissingMetaValue {
+                key: key.to_owned(),
+            },
+        )
+    }
+
+    pub fn duplicate_meta_key(line: usize, key: &str) -> (usize, Self) {
+        (
+            line,
+            Self::DuplicateMetaKey {
+                key: key.to_owned(),
+            },
+        )
+    }
+
+    pub fn invalid_condition(line: usize, condition: &str) -> (usize, Self) {
+        (
+            line,
+            Self::InvalidCondition {
+                condition: condition.to_owned(),
+            },
+        )
+    }
+
+    pub fn invalid_link_modifier(line: usize, modifier: &str) -> (usize, Self) {
+        (
+            line,
+            Self::InvalidLinkModifier {
+                modifier: modifier.to_owned(),
+            },
+        )
+    }
+
+    pub fn invalid_weight(line: usize, weight: &str) -> (usize, Self) {
+        (
+            line,
+            Self::InvalidWeight {
+                weight: weight.to_owned(),
+            },
+        )
+    }
+
+    pub fn misplaced_directive(line: usize, block: DirectiveKind) -> (usize, Self) {
+        (line, Self::MisplacedDirective { block })
+    }
+
+    pub fn duplicate_snippet(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::DuplicateSnippet {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn unknown_snippet(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::UnknownSnippet {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn recursive_snippet(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::RecursiveSnippet {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn excessive_snippet_expansion(line: usize, name: &str) -> (usize, Self) {
+        (
+            line,
+            Self::ExcessiveSnippetExpansion {
+                name: name.to_owned(),
+            },
+        )
+    }
+
+    pub fn missing_snippet_argument(line: usize, snippet: &str, parameter: &str) -> (usize, Self) {
+        (
+            line,
+            Self::MissingSnippetArgument {
+                snippet: snippet.to_owned(),
+                parameter: parameter.to_owned(),
+            },
+        )
+    }
+
+    pub fn unknown_snippet_parameter(line: usize, snippet: &str, parameter: &str) -> (usize, Self) {
+        (
+            line,
+            Self::UnknownSnippetParameter {
+                snippet: snippet.to_owned(),
+                parameter: parameter.to_owned(),
+            },
+        )
+    }
+
+    pub fn unknown_parent_page(line: usize, page: &str, parent: &str) -> (usize, Self) {
+        (
+            line,
+            Self::UnknownParentPage {
+                page: page.to_owned(),
+                parent: parent.to_owned(),
+            },
+        )
+    }
+
+    pub fn cyclic_inheritance(
+        line: usize,
+        page: &str,
+        parent: &str,
+        parent_line: usize,
+    ) -> (usize, Self) {
+        (
+            line,
+            Self::CyclicInheritance {
+                page: page.to_owned(),
+                parent: parent.to_owned(),
+                parent_line,
+            },
+        )
+    }
+
     pub fn unexpected_child_directive(line: usize, block: DirectiveKind) -> (usize, Self) {
         (line, Self::UnexpectedChildDirective { block })
     }
@@ -69,9 +345,18 @@ impl Error {
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::InvalidIndentation { count } => write!(
+            Error::InvalidIndentation { count, width } => write!(
+                f,
+                "Invalid indentation, expected a multiple of {width} spaces, but saw {count}."
+            ),
+            Error::MixedIndentation { expected } => write!(
+                f,
+                "Mixed indentation, the script is indented with {expected}, but this line is not."
+            ),
+            Error::UnknownIndentation { count } => write!(
                 f,
-                "Invalid indentation, expected a multiple of four spaces, but saw {count}."
+                "Unknown indentation, scripts are indented with 2 or 4 spaces, or tabs, but the \
+                first indented line has {count} spaces."
             ),
             Error::UnexpectedIndenation { expected, found } => write!(
                 f,
@@ -80,6 +365,34 @@ impl std::fmt::Display for Error {
             Error::MissingText { block } => {
                 write!(f, "A {block} directive has no text, but requires it.")
             }
+            Error::UnexpectedChildren { block } => {
+                write!(f, "The {block} directive cannot have any children.")
+            }
+            Error::MissingMetaValue { key } => {
+                write!(f, "The meta directive for {key:?} requires a value.")
+            }
+            Error::DuplicateMetaKey { key } => {
+                write!(f, "The meta key {key:?} is declared more than once.")
+            }
+            Error::InvalidCondition { condition } => write!(
+                f,
+                "Invalid condition {condition:?}, expected `visited(page)` or `first-visit`, optionally preceded by `not`."
+            ),
+            Error::InvalidWeight { weight } => write!(
+                f,
+                "Invalid weight {weight:?}, expected a whole number greater than zero."
+            ),
+            Error::InvalidLinkModifier { modifier } => write!(
+                f,
+                "Invalid link modifier {modifier:?}. A link's target can be followed by `once`, `hidden-unless` with a flag, `disabled` with a quoted reason, and lastly `if` with a condition."
+            ),
+            Error::ConditionalOutcome => write!(
+                f,
+                "Links within a random directive cannot have conditions or modifiers, but the random directive itself can have a condition."
+            ),
+            Error::MissingOutcomes => {
+                write!(f, "A random directive requires at least one link.")
+            }
             Error::UnexpectedArgument { block } => {
                 write!(f, "A {block} directive cannot have an argument.")
             }
@@ -101,13 +414,74 @@ impl std::fmt::Display for Error {
             Error::ExcessivePageTitles { page } => {
                 write!(f, "The page {page:?} has too many declared titles.")
             }
+            Error::ExcessivePageTemplates { page } => {
+                write!(f, "The page {page:?} has too many declared templates.")
+            }
             Error::UnexpectedText => write!(f, "Unexpected text."),
             Error::NestedPage { parent, child } => {
                 write!(f, "Page {child:?} is nested inside of {parent:?}.")
             }
+            Error::UnknownParentPage { page, parent } => write!(
+                f,
+                "The page {page:?} extends the page {parent:?}, but it does not exist."
+            ),
+            Error::CyclicInheritance {
+                page,
+                parent,
+                parent_line,
+            } => write!(
+                f,
+                "The page {page:?} extends the page {parent:?} (line {}), which leads back to {page:?}.",
+                parent_line + 1
+            ),
             Error::NonPageTopLevelBlock => {
-                write!(f, "Only pages are allowed as top-level directives.")
+                write!(
+                    f,
+                    "Only pages, chapters, snippets and meta are allowed as top-level directives."
+                )
+            }
+            Error::MisplacedDirective { block } => match block {
+                DirectiveKind::Snippet => {
+                    write!(f, "Snippets can only be declared at the top level.")
+                }
+                DirectiveKind::Use => write!(f, "Snippets can only be used within text."),
+                _ => write!(f, "The {block} directive cannot be used here."),
+            },
+            Error::NonPageChapterBlock { chapter } => write!(
+                f,
+                "Only pages and a title are allowed within the chapter {chapter:?}."
+            ),
+            Error::InvalidChapterName { name } => write!(
+                f,
+                "The chapter name {name:?} is invalid. Chapters name directories, so their names can't contain slashes or whitespace, or be `.` or `..`."
+            ),
+            Error::ExcessiveChapterTitles { chapter } => {
+                write!(f, "The chapter {chapter:?} has more than one title.")
+            }
+            Error::DuplicateChapter { name } => {
+                write!(f, "The chapter {name:?} is declared more than once.")
             }
+            Error::DuplicateSnippet { name } => {
+                write!(f, "The snippet {name:?} is declared more than once.")
+            }
+            Error::UnknownSnippet { name } => {
+                write!(f, "The snippet {name:?} is used, but it does not exist.")
+            }
+            Error::RecursiveSnippet { name } => {
+                write!(f, "The snippet {name:?} is used within itself.")
+            }
+            Error::ExcessiveSnippetExpansion { name } => write!(
+                f,
+                "The snippet {name:?} expands to more lines than are allowed in a single text block."
+            ),
+            Error::MissingSnippetArgument { snippet, parameter } => write!(
+                f,
+                "The snippet {snippet:?} is used without a value for its parameter {parameter:?}."
+            ),
+            Error::UnknownSnippetParameter { snippet, parameter } => write!(
+                f,
+                "The snippet {snippet:?} is used with a value for {parameter:?}, but it has no such parameter."
+            ),
         }
     }
 }
@@ -0,0 +1,319 @@
+use super::Error;
+use crate::script::{Condition, Link, Page};
+use std::borrow::Cow;
+
+/// The state of a page while resolving inheritance.
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unresolved,
+    Resolving,
+    Resolved,
+}
+
+/// Merge the content of each page which extends another with the content of that page. The lines
+/// of the pages are used to report unknown pages, and pages which end up extending themselves.
+pub fn resolve(pages: &mut [Page], lines: &[usize]) -> Result<(), Vec<(usize, Error)>> {
+    let mut states = vec![State::Unresolved; pages.len()];
+    let mut errors = Vec::new();
+
+    for index in 0..pages.len() {
+        resolve_page(index, pages, lines, &mut states, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Resolve a single page, after the page it extends. Returns whether the page could be resolved.
+fn resolve_page(
+    index: usize,
+    pages: &mut [Page],
+    lines: &[usize],
+    states: &mut [State],
+    errors: &mut Vec<(usize, Error)>,
+) -> bool {
+    match states[index] {
+        State::Resolved => return true,
+        // Reaching a page which is still being resolved means it extends itself, which is reported
+        // by the page which closes the loop.
+        State::Resolving => return false,
+        State::Unresolved => {}
+    }
+
+    let parent = match pages[index].extends {
+        Some(parent) => parent,
+        None => {
+            states[index] = State::Resolved;
+            return true;
+        }
+    };
+
+    let page = &pages[index].identifier;
+    let parent_index = match pages[index].find(pages, parent) {
+        Some(parent_index) => parent_index,
+        None => {
+            errors.push(Error::unknown_parent_page(lines[index], page, parent));
+            states[index] = State::Resolved;
+            return false;
+        }
+    };
+
+    states[index] = State::Resolving;
+
+    if states[parent_index] == State::Resolving {
+        let error = Error::cyclic_inheritance(lines[index], page, parent, lines[parent_index]);
+        errors.push(error);
+        states[index] = State::Resolved;
+        return false;
+    }
+
+    let resolved = resolve_page(parent_index, pages, lines, states, errors);
+    states[index] = State::Resolved;
+
+    if resolved {
+        let merged = merge(&pages[parent_index], &pages[index], pages);
+        pages[index] = merged;
+    }
+
+    resolved
+}
+
+/// Make a page from the page it extends. The title and template of the page override those of
+/// its parent, as do metadata with the same key and links to the same page. Everything else is
+/// added after what's inherited. Pages are referred to by their full identifiers, since the parent
+/// may be in another chapter.
+fn merge<'a>(parent: &Page<'a>, page: &Page<'a>, pages: &[Page<'a>]) -> Page<'a> {
+    let title = if page.title.is_empty() {
+        parent.title
+    } else {
+        page.title
+    };
+
+    let mut paragraphs = parent.paragraphs.clone();
+    paragraphs.extend(page.paragraphs.iter().cloned());
+
+    let paragraph_conditions = [parent, page]
+        .into_iter()
+        .flat_map(|from| {
+            let conditions = from.paragraph_conditions.iter();
+            conditions.map(move |condition| qualify_condition(from, pages, condition))
+        })
+        .collect();
+
+    let mut links = qualify_links(parent, pages);
+    for link in qualify_links(page, pages) {
+        match links
+            .iter()
+            .position(|existing| existing.target == link.target)
+        {
+            Some(position) => links[position] = link,
+            None => links.push(link),
+        }
+    }
+
+    let mut random = Vec::with_capacity(parent.random.len() + page.random.len());
+    for from in [parent, page] {
+        for choice in &from.random {
+            let mut choice = choice.clone();
+            choice.condition = qualify_condition(from, pages, &choice.condition);
+            for outcome in &mut choice.outcomes {
+                outcome.target = qualify(from, pages, &outcome.target);
+            }
+            random.push(choice);
+        }
+    }
+
+    let mut meta = parent.meta.clone();
+    for (key, value) in &page.meta {
+        match meta.iter_mut().find(|(existing, _)| existing == key) {
+            Some(pair) => pair.1 = value,
+            None => meta.push((key, value)),
+        }
+    }
+
+    Page {
+        identifier: page.identifier.clone(),
+        chapter: page.chapter,
+        title,
+        paragraphs,
+        paragraph_conditions,
+        links,
+        random,
+        meta,
+        template: page.template.or(parent.template),
+        extends: page.extends,
+    }
+}
+
+/// The full identifier of the page which a page refers to. Pages which don't exist are left as
+/// they are, to be reported along with every other unknown page.
+fn qualify<'a>(from: &Page<'a>, pages: &[Page<'a>], target: &Cow<'a, str>) -> Cow<'a, str> {
+    match from.find(pages, target) {
+        Some(position) => pages[position].identifier.clone(),
+        None => target.clone(),
+    }
+}
+
+fn qualify_condition<'a>(
+    from: &Page<'a>,
+    pages: &[Page<'a>],
+    condition: &Option<Condition<'a>>,
+) -> Option<Condition<'a>> {
+    let condition = match condition.as_ref()? {
+        Condition::Visited(target) => Condition::Visited(qualify(from, pages, target)),
+        Condition::NotVisited(target) => Condition::NotVisited(qualify(from, pages, target)),
+        condition => condition.clone(),
+    };
+    Some(condition)
+}
+
+fn qualify_links<'a>(from: &Page<'a>, pages: &[Page<'a>]) -> Vec<Link<'a>> {
+    let links = from.links.iter().map(|link| Link {
+        target: qualify(from, pages, &link.target),
+        condition: qualify_condition(from, pages, &link.condition),
+        ..link.clone()
+    });
+    links.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::{parse::Error, Condition, Script};
+
+    #[test]
+    fn can_extend_pages() {
+        let script = Script::new(
+            "page cellar-2 extends cellar
+    text
+        The rats have gone.
+    link hall
+        Go back up the stairs.
+    link garden
+        Climb out of the window.
+    meta mood calm
+
+page cellar
+    title
+        The Cellar
+    template dark
+    meta mood spooky
+    meta music drip.ogg
+    text
+        It's damp down here.
+    link hall
+        Go back up.
+
+page hall
+    title
+        The Hall
+
+page garden extends hall
+    title
+        The Garden
+",
+            None,
+        )
+        .unwrap();
+
+        let cellar = &script.pages[0];
+        assert_eq!("The Cellar", cellar.title);
+        assert_eq!(Some("dark"), cellar.template);
+        assert_eq!(
+            vec!["It's damp down here.", "The rats have gone."],
+            cellar.paragraphs
+        );
+        let links: Vec<_> = cellar
+            .links
+            .iter()
+            .map(|l| (l.target.as_ref(), l.text))
+            .collect();
+        assert_eq!(
+            vec![
+                ("hall", "Go back up the stairs."),
+                ("garden", "Climb out of the window.")
+            ],
+            links
+        );
+        assert_eq!(vec![("mood", "calm"), ("music", "drip.ogg")], cellar.meta);
+
+        assert_eq!("The Garden", script.pages[3].title);
+    }
+
+    #[test]
+    fn can_extend_pages_in_other_chapters() {
+        let script = Script::new(
+            "chapter one
+    page cave
+        title
+            The Cave
+        text if visited(exit)
+            You've been out before.
+        link exit if not visited(exit)
+            Leave.
+        random
+            Wander.
+            link exit
+                Find the way out.
+
+    page exit
+        title
+            The Exit
+
+chapter two
+    page cave extends one/cave
+        link exit
+            Leave the other way.
+
+    page exit
+        title
+            The Other Exit
+",
+            None,
+        )
+        .unwrap();
+
+        let visited = Condition::Visited("one/exit".into());
+        let not_visited = Condition::NotVisited("one/exit".into());
+
+        let cave = &script.pages[2];
+        assert_eq!("two/cave", cave.identifier);
+        assert_eq!(vec![Some(visited)], cave.paragraph_conditions);
+
+        let links: Vec<_> = cave.links.iter().map(|l| l.target.as_ref()).collect();
+        assert_eq!(vec!["one/exit", "two/exit"], links);
+        assert_eq!(Some(not_visited), cave.links[0].condition);
+        assert_eq!("one/exit", cave.random[0].outcomes[0].target);
+    }
+
+    #[test]
+    fn report_unknown_and_cyclic_pages() {
+        let errors = Script::new(
+            "page a extends b
+    title
+        A
+
+page b extends a
+    title
+        B
+
+page c extends nowhere
+",
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(2, errors.len());
+        assert!(matches!(
+            &errors[0],
+            (4, Error::CyclicInheritance { page, parent, parent_line: 0 })
+                if page == "b" && parent == "a"
+        ));
+        assert!(matches!(
+            &errors[1],
+            (8, Error::UnknownParentPage { page, parent }) if page == "c" && parent == "nowhere"
+        ));
+    }
+}
@@ -8,6 +8,12 @@ pub enum DirectiveKind {
     Title,
     Link,
     Text,
+    Meta,
+    Template,
+    Random,
+    Snippet,
+    Use,
+    Chapter,
 }
 
 impl DirectiveKind {
@@ -17,18 +23,83 @@ impl DirectiveKind {
             "title" => Some(Self::Title),
             "link" => Some(Self::Link),
             "text" => Some(Self::Text),
+            "meta" => Some(Self::Meta),
+            "template" => Some(Self::Template),
+            "random" => Some(Self::Random),
+            "snippet" => Some(Self::Snippet),
+            "use" => Some(Self::Use),
+            "chapter" => Some(Self::Chapter),
             _ => None,
         }
     }
+
+    /// The keyword which starts the directive.
+    pub const fn keyword(self) -> &'static str {
+        match self {
+            DirectiveKind::Page => "page",
+            DirectiveKind::Title => "title",
+            DirectiveKind::Link => "link",
+            DirectiveKind::Text => "text",
+            DirectiveKind::Meta => "meta",
+            DirectiveKind::Template => "template",
+            DirectiveKind::Random => "random",
+            DirectiveKind::Snippet => "snippet",
+            DirectiveKind::Use => "use",
+            DirectiveKind::Chapter => "chapter",
+        }
+    }
 }
 
 impl std::fmt::Display for DirectiveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.keyword())
+    }
+}
+
+/// The characters used to indent each level of a script.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indentation {
+    /// A fixed number of spaces per level.
+    Spaces(usize),
+
+    /// A single tab character per level.
+    Tab,
+}
+
+impl Indentation {
+    /// Guess the indentation style of a line from its leading whitespace, taking it to be indented
+    /// by a single level. Lines which aren't indented, and blank or comment lines, tell us nothing,
+    /// so return `None`. Only 2 or 4 spaces are a level, so any other number is an error, rather
+    /// than a style which every correctly indented line after it would break.
+    pub fn detect(line: &str) -> Result<Option<Self>, Error> {
+        let rest = line.trim_start_matches([' ', '\t']);
+
+        if rest.is_empty() || rest.starts_with('#') {
+            return Ok(None);
+        }
+
+        match line.chars().next() {
+            Some('\t') => Ok(Some(Self::Tab)),
+            Some(' ') => match line.len() - line.trim_start_matches(' ').len() {
+                count @ (2 | 4) => Ok(Some(Self::Spaces(count))),
+                count => Err(Error::UnknownIndentation { count }),
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+impl std::fmt::Display for Indentation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            DirectiveKind::Page => write!(f, "page"),
-            DirectiveKind::Title => write!(f, "title"),
-            DirectiveKind::Link => write!(f, "link"),
-            DirectiveKind::Text => write!(f, "text"),
+            Indentation::Spaces(count) => write!(f, "{count} spaces"),
+            Indentation::Tab => write!(f, "tabs"),
         }
     }
 }
@@ -46,10 +117,10 @@ pub struct Line<'a> {
 }
 
 impl<'a> Line<'a> {
-    /// Parse a single line from a string. An empty line is considered successful, which
-    /// returns `Ok(None)`.
-    pub fn parse(line: &'a str) -> Result<Option<Self>, Error> {
-        let (indent, command) = Self::take_whitespace(line)?;
+    /// Parse a single line from a string, indented with the given style. An empty line is
+    /// considered successful, which returns `Ok(None)`.
+    pub fn parse(line: &'a str, indentation: Indentation) -> Result<Option<Self>, Error> {
+        let (indent, command) = Self::take_whitespace(line, indentation)?;
 
         let (word, rest) = Self::take_word(command);
 
@@ -59,6 +130,10 @@ impl<'a> Line<'a> {
         }
 
         let kind = match DirectiveKind::from_str(word) {
+            // Meta values are kept whole, so they can hold colours like `#ff0000`, or URLs.
+            Some(DirectiveKind::Meta) => {
+                LineKind::Directive(DirectiveKind::Meta, Self::non_empty(rest.trim()))
+            }
             Some(kind) => LineKind::Directive(kind, Self::skip_whitespace(rest)),
             None => LineKind::Text(command),
         };
@@ -66,7 +141,7 @@ impl<'a> Line<'a> {
         Ok(Some(Self { indent, kind }))
     }
 
-    #[allow(dead_code)]
+    #[cfg(test)]
     pub const fn new_text(indent: usize, text: &'a str) -> Self {
         Self {
             indent,
@@ -74,7 +149,7 @@ impl<'a> Line<'a> {
         }
     }
 
-    #[allow(dead_code)]
+    #[cfg(test)]
     pub const fn new_directive(indent: usize, kind: DirectiveKind, text: Option<&'a str>) -> Self {
         Self {
             indent,
@@ -82,29 +157,38 @@ impl<'a> Line<'a> {
         }
     }
 
-    /// Attempt to read `4n` space characters. On success, return `n` and the rest of the string.
-    /// Otherwise, return the encountered errors.
-    fn take_whitespace(input: &str) -> Result<(usize, &str), Error> {
-        let mut count = 0;
+    /// Attempt to read `n` levels of indentation in the given style. On success, return `n` and the
+    /// rest of the string. Otherwise, return the encountered errors.
+    fn take_whitespace(input: &str, indentation: Indentation) -> Result<(usize, &str), Error> {
+        let rest = input.trim_start_matches([' ', '\t']);
 
-        for (index, c) in input.char_indices() {
-            if c == ' ' {
-                count += 1;
-            } else if c == '#' {
-                // This is a comment line, so we'll report it as empty. We can ignore potentially
-                // incorrect indentation of comments, as they don't matter syntactically.
-                return Ok((count / 4, ""));
-            } else if (count % 4) == 0 {
-                let rest = &input[index..];
-                return Ok((count / 4, rest));
-            } else {
-                return Err(Error::InvalidIndentation { count });
-            }
+        // If the string only contained whitespace, it'll be discarded as empty. Likewise, comment
+        // lines are reported as empty. Neither matter syntactically, so we don't bother reporting
+        // incorrect indentation for them.
+        if rest.is_empty() || rest.starts_with('#') {
+            return Ok((0, ""));
         }
 
-        // If the string only contained spaces, it'll be discared as empty. Reporting an empty line
-        // which happens to have bad indentation will be annoying, so we silently pass them along.
-        Ok((count / 4, ""))
+        let prefix = &input[..input.len() - rest.len()];
+        let tabs = prefix.matches('\t').count();
+        let spaces = prefix.len() - tabs;
+
+        match indentation {
+            Indentation::Tab if spaces == 0 => Ok((tabs, rest)),
+            Indentation::Spaces(_) if tabs > 0 => Err(Error::MixedIndentation {
+                expected: indentation,
+            }),
+            Indentation::Spaces(width) => match (spaces / width, spaces % width) {
+                (levels, 0) => Ok((levels, rest)),
+                _ => Err(Error::InvalidIndentation {
+                    count: spaces,
+                    width,
+                }),
+            },
+            Indentation::Tab => Err(Error::MixedIndentation {
+                expected: indentation,
+            }),
+        }
     }
 
     /// Read the string until the first whitespace character (exclusive). Return the read string,
@@ -122,7 +206,7 @@ impl<'a> Line<'a> {
 
     /// Return the string before a comment, if any.
     fn trim_comment(input: &str) -> &str {
-        if let Some((before, _)) = input.split_once(|c| c == '#') {
+        if let Some((before, _)) = input.split_once('#') {
             before
         } else {
             input
@@ -146,6 +230,7 @@ impl<'a> Line<'a> {
 
 pub struct Lines<'a> {
     inner: Enumerate<std::str::Lines<'a>>,
+    indentation: Option<Indentation>,
 }
 
 impl<'a> Iterator for Lines<'a> {
@@ -153,7 +238,15 @@ impl<'a> Iterator for Lines<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         for (index, string) in self.inner.by_ref() {
-            let parsed = Line::parse(string);
+            // Unless configured otherwise, the first indented line decides the style for the rest.
+            if self.indentation.is_none() {
+                match Indentation::detect(string) {
+                    Ok(detected) => self.indentation = detected,
+                    Err(e) => return Some((index, Err(e))),
+                }
+            }
+
+            let parsed = Line::parse(string, self.indentation.unwrap_or_default());
 
             match parsed {
                 Ok(Some(l)) => return Some((index, Ok(l))),
@@ -167,16 +260,17 @@ impl<'a> Iterator for Lines<'a> {
 }
 
 impl<'a> Lines<'a> {
-    pub fn new(string: &'a str) -> Self {
+    pub fn new(string: &'a str, indentation: Option<Indentation>) -> Self {
         Self {
             inner: string.lines().enumerate(),
+            indentation,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{super::Error, DirectiveKind, Line, Lines};
+    use super::{super::Error, DirectiveKind, Indentation, Line, Lines};
 
     #[test]
     fn can_get_directive_kinds() {
@@ -185,6 +279,8 @@ mod tests {
             ("title", Some(DirectiveKind::Title)),
             ("link", Some(DirectiveKind::Link)),
             ("text", Some(DirectiveKind::Text)),
+            ("meta", Some(DirectiveKind::Meta)),
+            ("template", Some(DirectiveKind::Template)),
             ("", None),
             ("pag", None),
             ("links", None),
@@ -202,7 +298,7 @@ mod tests {
         const EMPTY_LINES: &[&str] = &["", " ", "  ", "   ", "    ", "     "];
 
         for line in EMPTY_LINES {
-            let result = Line::parse(line);
+            let result = Line::parse(line, Indentation::default());
             let matches = matches!(result, Ok(None));
             assert!(matches);
         }
@@ -213,7 +309,7 @@ mod tests {
         const COMMENTS: &[&str] = &["# ...", "     # nothing!"];
 
         for line in COMMENTS {
-            let result = Line::parse(line);
+            let result = Line::parse(line, Indentation::default());
             assert!(matches!(result, Ok(None)));
         }
     }
@@ -242,10 +338,18 @@ mod tests {
                 "    title Another directive example   #   and comments!?   ",
                 Line::new_directive(1, DirectiveKind::Title, Some("Another directive example")),
             ),
+            (
+                "    meta colour #ff0000 # not a comment",
+                Line::new_directive(
+                    1,
+                    DirectiveKind::Meta,
+                    Some("colour #ff0000 # not a comment"),
+                ),
+            ),
         ];
 
         for (input, expected) in EXAMPLES {
-            let actual = Line::parse(input).unwrap().unwrap();
+            let actual = Line::parse(input, Indentation::default()).unwrap().unwrap();
             assert_eq!(expected, &actual);
         }
     }
@@ -262,10 +366,10 @@ mod tests {
         ];
 
         for (input, expected) in EXAMPLES {
-            let actual = Line::parse(input).unwrap_err();
+            let actual = Line::parse(input, Indentation::default()).unwrap_err();
             assert!(matches!(
                 actual,
-                Error::InvalidIndentation { count } if count == *expected
+                Error::InvalidIndentation { count, width: 4 } if count == *expected
             ));
         }
     }
@@ -273,7 +377,125 @@ mod tests {
     #[test]
     fn empty_string_has_no_lines() {
         let input = "\n\n    \n\n               \n\n\n\n    \n";
-        let output: Vec<_> = Lines::new(input).collect();
+        let output: Vec<_> = Lines::new(input, None).collect();
         assert!(output.is_empty());
     }
+
+    #[test]
+    fn can_parse_other_indentation_styles() {
+        const EXAMPLES: &[(&str, Indentation, Line)] = &[
+            (
+                "\ttitle Hello!",
+                Indentation::Tab,
+                Line::new_directive(1, DirectiveKind::Title, Some("Hello!")),
+            ),
+            (
+                "\t\t\tSome text",
+                Indentation::Tab,
+                Line::new_text(3, "Some text"),
+            ),
+            (
+                "    link two-levels",
+                Indentation::Spaces(2),
+                Line::new_directive(2, DirectiveKind::Link, Some("two-levels")),
+            ),
+            (
+                "  text",
+                Indentation::Spaces(2),
+                Line::new_directive(1, DirectiveKind::Text, None),
+            ),
+        ];
+
+        for (input, indentation, expected) in EXAMPLES {
+            let actual = Line::parse(input, *indentation).unwrap().unwrap();
+            assert_eq!(expected, &actual);
+        }
+    }
+
+    #[test]
+    fn can_generate_mixed_indentation_errors() {
+        const EXAMPLES: &[(&str, Indentation)] = &[
+            ("\ttext", Indentation::Spaces(4)),
+            ("    \ttext", Indentation::Spaces(4)),
+            ("    text", Indentation::Tab),
+            ("\t  text", Indentation::Tab),
+        ];
+
+        for (input, indentation) in EXAMPLES {
+            let actual = Line::parse(input, *indentation).unwrap_err();
+            assert!(matches!(
+                actual,
+                Error::MixedIndentation { expected } if expected == *indentation
+            ));
+        }
+    }
+
+    #[test]
+    fn ignore_indentation_of_comments_and_blank_lines() {
+        const EXAMPLES: &[&str] = &["\t", "  \t  ", "\t# comment", "   # comment"];
+
+        for indentation in [Indentation::Tab, Indentation::Spaces(4)] {
+            for line in EXAMPLES {
+                assert!(matches!(Line::parse(line, indentation), Ok(None)));
+            }
+        }
+    }
+
+    #[test]
+    fn can_detect_indentation_style() {
+        let tabs = "page a\n\n    # comment\n\ttitle\n\t\tA\n";
+        let output: Vec<_> = Lines::new(tabs, None).collect();
+        assert_eq!(3, output.len());
+        assert!(output.iter().all(|(_, result)| result.is_ok()));
+
+        let mixed = "page a\n\ttitle\n\t\tA\n    text\n\t\tB\n";
+        let output: Vec<_> = Lines::new(mixed, None).collect();
+        let errors: Vec<_> = output
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .collect();
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            errors[0],
+            (
+                3,
+                Err(Error::MixedIndentation {
+                    expected: Indentation::Tab
+                })
+            )
+        ));
+    }
+
+    #[test]
+    fn can_detect_indentation_width() {
+        assert_eq!(
+            Some(Indentation::Spaces(2)),
+            Indentation::detect("  title").unwrap()
+        );
+        assert_eq!(
+            Some(Indentation::Tab),
+            Indentation::detect("\ttitle").unwrap()
+        );
+        assert_eq!(None, Indentation::detect("    # comment").unwrap());
+
+        let input = "page a\n  title\n    A\n";
+        assert!(Lines::new(input, None).all(|(_, result)| result.is_ok()));
+
+        // An over-indented first line is reported, and the lines after it still decide the style.
+        let input = "page a\n        title\n    text\n        A\n";
+        let output: Vec<_> = Lines::new(input, None).collect();
+        assert!(matches!(
+            output[1],
+            (1, Err(Error::UnknownIndentation { count: 8 }))
+        ));
+        assert!(output[2..].iter().all(|(_, result)| result.is_ok()));
+    }
+
+    #[test]
+    fn configured_indentation_overrides_detection() {
+        let input = "page a\n  title\n    A\n";
+
+        assert!(Lines::new(input, Some(Indentation::Spaces(2))).all(|(_, result)| result.is_ok()));
+        assert!(Lines::new(input, Some(Indentation::Spaces(4))).any(|(_, result)| result.is_err()));
+    }
 }
@@ -1,23 +1,32 @@
 mod block;
+mod chapter;
 mod error;
+mod inherit;
 mod line;
 mod page;
+mod snippet;
+pub mod syntax;
 
 use self::{
-    block::Block,
+    block::{Block, BlockKind},
     line::{Line, Lines},
     page::PageBlock,
+    snippet::{Snippet, Snippets},
 };
-use super::{Page, Script};
+use super::{Chapter, Script};
 pub use error::Error;
+pub use line::{DirectiveKind, Indentation, LineKind};
 
 type Result<T> = std::result::Result<T, Vec<(usize, Error)>>;
 
-fn string_to_lines(string: &str) -> Result<Vec<(usize, Line)>> {
+fn string_to_lines(
+    string: &str,
+    indentation: Option<Indentation>,
+) -> Result<Vec<(usize, Line<'_>)>> {
     let mut lines = Vec::new();
     let mut errors = Vec::new();
 
-    for (number, result) in Lines::new(string) {
+    for (number, result) in Lines::new(string, indentation) {
         match result {
             Ok(o) => lines.push((number, o)),
             Err(e) => errors.push((number, e)),
@@ -35,28 +44,104 @@ fn lines_to_blocks(lines: Vec<(usize, Line)>) -> Result<Vec<Block>> {
     Block::parse(lines)
 }
 
-fn blocks_to_pages(blocks: Vec<Block>) -> Result<Vec<Page>> {
+/// Take the snippets from the top level of a script, leaving every other block. Every snippet is
+/// checked here, so problems within snippets are reported once, rather than at each use.
+fn take_snippets<'a>(
+    blocks: Vec<Block<'a>>,
+    errors: &mut Vec<(usize, Error)>,
+) -> (Snippets<'a>, Vec<Block<'a>>) {
+    let mut snippets = Snippets::new();
+    let mut rest = Vec::with_capacity(blocks.len());
+
+    for block in blocks {
+        let internal = match block.kind {
+            BlockKind::Internal(internal) if internal.kind == DirectiveKind::Snippet => internal,
+            kind => {
+                rest.push(Block { kind, ..block });
+                continue;
+            }
+        };
+
+        match Snippet::parse(block.line, internal.argument, internal.children) {
+            Ok((name, _)) if snippets.contains_key(name) => {
+                errors.push(Error::duplicate_snippet(block.line, name));
+            }
+            Ok((name, snippet)) => {
+                snippets.insert(name, snippet);
+            }
+            Err(new_errors) => errors.extend(new_errors),
+        }
+    }
+
+    snippet::validate(&snippets, errors);
+    (snippets, rest)
+}
+
+fn blocks_to_script(blocks: Vec<Block>) -> Result<Script> {
     let mut pages = Vec::with_capacity(blocks.len());
+    let mut page_lines = Vec::with_capacity(blocks.len());
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut meta = Vec::new();
     let mut errors = Vec::new();
 
+    // Snippets can be used before they're declared, so they're all found first.
+    let (snippets, blocks) = take_snippets(blocks, &mut errors);
+
     for block in blocks {
-        match PageBlock::parse(block) {
-            Ok((_, PageBlock::Page(page))) => pages.push(page),
+        let block = match block.kind {
+            BlockKind::Internal(internal) if internal.kind == DirectiveKind::Chapter => {
+                let line = block.line;
+                match Chapter::parse(line, internal.argument, internal.children, &snippets) {
+                    Ok((chapter, _)) if chapters.iter().any(|c| c.name == chapter.name) => {
+                        errors.push(Error::duplicate_chapter(line, chapter.name));
+                    }
+                    Ok((chapter, chapter_pages)) => {
+                        chapters.push(chapter);
+                        for (n, page) in chapter_pages {
+                            pages.push(page);
+                            page_lines.push(n);
+                        }
+                    }
+                    Err(e) => errors.extend(e),
+                }
+                continue;
+            }
+            kind => Block { kind, ..block },
+        };
+
+        match PageBlock::parse(block, &snippets) {
+            Ok((n, PageBlock::Page(page))) => {
+                pages.push(page);
+                page_lines.push(n);
+            }
+            Ok((n, PageBlock::Meta(key, value))) => {
+                if let Err(e) = PageBlock::add_meta(&mut meta, n, key, value) {
+                    errors.push(e);
+                }
+            }
             Ok((n, _)) => errors.push((n, Error::NonPageTopLevelBlock)),
             Err(e) => errors.extend(e),
         }
     }
 
+    // Inheritance is only resolved once every page is parsed, as pages can extend later pages.
+    if errors.is_empty() {
+        inherit::resolve(&mut pages, &page_lines)?;
+    }
+
     if errors.is_empty() {
-        Ok(pages)
+        Ok(Script {
+            pages,
+            chapters,
+            meta,
+        })
     } else {
         Err(errors)
     }
 }
 
-pub fn parse(input: &str) -> Result<Script> {
-    let lines = string_to_lines(input)?;
+pub fn parse(input: &str, indentation: Option<Indentation>) -> Result<Script<'_>> {
+    let lines = string_to_lines(input, indentation)?;
     let blocks = lines_to_blocks(lines)?;
-    let pages = blocks_to_pages(blocks)?;
-    Ok(Script { pages })
+    blocks_to_script(blocks)
 }
@@ -1,23 +1,33 @@
-use crate::script::Page;
+use std::borrow::Cow;
+
+use crate::script::{Condition, Link, Outcome, Page, Random};
 
 use super::{
     block::{BlockKind, InternalBlock},
     line::DirectiveKind,
+    snippet::{Item, Snippets},
     Block, Error,
 };
 
 #[derive(Debug)]
 pub enum PageBlock<'a> {
     Title(&'a str),
-    Link(&'a str, &'a str),
-    Text(Vec<&'a str>),
+    Link(Link<'a>),
+    Text(Vec<Cow<'a, str>>, Option<Condition<'a>>),
+    Meta(&'a str, &'a str),
+    Template(&'a str),
+    Random(Random<'a>),
     Page(Page<'a>),
 }
 
 impl<'a> PageBlock<'a> {
-    pub fn parse(block: Block<'a>) -> Result<(usize, PageBlock), Vec<(usize, Error)>> {
+    /// Parse a block, expanding the uses of the given snippets within text blocks.
+    pub fn parse(
+        block: Block<'a>,
+        snippets: &Snippets<'a>,
+    ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
         match block.kind {
-            BlockKind::Internal(internal) => Self::internal(block.line, internal),
+            BlockKind::Internal(internal) => Self::internal(block.line, internal, snippets),
             BlockKind::External(_) => Self::external(block.line),
         }
     }
@@ -25,12 +35,36 @@ impl<'a> PageBlock<'a> {
     fn internal(
         line: usize,
         block: InternalBlock<'a>,
-    ) -> Result<(usize, PageBlock), Vec<(usize, Error)>> {
+        snippets: &Snippets<'a>,
+    ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
         match block.kind {
-            DirectiveKind::Page => Self::page(line, block.argument, block.children),
+            DirectiveKind::Page => Self::page(line, block.argument, block.children, snippets),
             DirectiveKind::Title => Self::title(line, block.argument, block.children),
             DirectiveKind::Link => Self::link(line, block.argument, block.children),
-            DirectiveKind::Text => Self::text(line, block.argument, block.children),
+            DirectiveKind::Text => Self::text(line, block.argument, block.children, snippets),
+            DirectiveKind::Meta => Self::meta(line, block.argument, block.children),
+            DirectiveKind::Template => Self::template(line, block.argument, block.children),
+            DirectiveKind::Random => Self::random(line, block.argument, block.children),
+            // Snippets and chapters are only found at the top level, and uses only within text
+            // blocks.
+            DirectiveKind::Snippet | DirectiveKind::Use | DirectiveKind::Chapter => {
+                Err(vec![Error::misplaced_directive(line, block.kind)])
+            }
+        }
+    }
+
+    /// Add a key-value pair to a list of metadata, reporting keys which were already added.
+    pub fn add_meta(
+        meta: &mut Vec<(&'a str, &'a str)>,
+        line: usize,
+        key: &'a str,
+        value: &'a str,
+    ) -> Result<(), (usize, Error)> {
+        if meta.iter().any(|(existing, _)| *existing == key) {
+            Err(Error::duplicate_meta_key(line, key))
+        } else {
+            meta.push((key, value));
+            Ok(())
         }
     }
 
@@ -42,31 +76,54 @@ impl<'a> PageBlock<'a> {
         line: usize,
         argument: Option<&'a str>,
         children: Vec<Block<'a>>,
+        snippets: &Snippets<'a>,
     ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
         let mut errors = Vec::new();
 
-        let identifier = argument.unwrap_or_else(|| {
+        let argument = argument.unwrap_or_else(|| {
             errors.push(Error::missing_argument(line, DirectiveKind::Page));
             "{unnamed}"
         });
 
+        // A page can extend another, such as `page cellar-2 extends cellar`.
+        let (identifier, extends) = match argument.split_once(" extends ") {
+            Some((identifier, parent)) => (identifier.trim_end(), Some(parent.trim())),
+            None => (argument, None),
+        };
+
         let mut titles = Vec::with_capacity(1);
         let mut paragraphs = Vec::new();
+        let mut paragraph_conditions = Vec::new();
         let mut links = Vec::new();
+        let mut random = Vec::new();
+        let mut meta = Vec::new();
+        let mut templates = Vec::with_capacity(1);
 
         for child in children {
-            match Self::parse(child) {
+            match Self::parse(child, snippets) {
                 Ok((_, PageBlock::Title(title))) => titles.push(title),
-                Ok((_, PageBlock::Text(text))) => paragraphs.extend(text),
-                Ok((_, PageBlock::Link(target, text))) => links.push((target, text)),
+                Ok((_, PageBlock::Text(text, condition))) => {
+                    paragraph_conditions.extend(text.iter().map(|_| condition.clone()));
+                    paragraphs.extend(text);
+                }
+                Ok((_, PageBlock::Link(link))) => links.push(link),
+                Ok((_, PageBlock::Random(choice))) => random.push(choice),
+                Ok((_, PageBlock::Template(template))) => templates.push(template),
+                Ok((line, PageBlock::Meta(key, value))) => {
+                    if let Err(error) = Self::add_meta(&mut meta, line, key, value) {
+                        errors.push(error);
+                    }
+                }
                 Ok((line, PageBlock::Page(page))) => {
-                    errors.push(Error::nested_page(line, identifier, page.identifier))
+                    errors.push(Error::nested_page(line, identifier, &page.identifier))
                 }
                 Err(new_errors) => errors.extend(new_errors),
             }
         }
 
         let title = match titles.as_slice() {
+            // Pages which extend another can take its title, once it's known.
+            [] if extends.is_some() => "",
             [] => {
                 errors.push(Error::page_missing_title(line, identifier));
                 "{untitled}"
@@ -78,12 +135,27 @@ impl<'a> PageBlock<'a> {
             }
         };
 
+        let template = match templates.as_slice() {
+            [] => None,
+            [t] => Some(*t),
+            [first, ..] => {
+                errors.push(Error::excessive_page_templates(line, identifier));
+                Some(*first)
+            }
+        };
+
         if errors.is_empty() {
             let page = PageBlock::Page(Page {
-                identifier,
+                identifier: Cow::Borrowed(identifier),
+                chapter: None,
                 title,
                 paragraphs,
+                paragraph_conditions,
                 links,
+                random,
+                meta,
+                template,
+                extends,
             });
             Ok((line, page))
         } else {
@@ -95,33 +167,21 @@ impl<'a> PageBlock<'a> {
         line: usize,
         argument: Option<&'a str>,
         children: Vec<Block<'a>>,
+        snippets: &Snippets<'a>,
     ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
         let mut errors = Vec::new();
 
-        if argument.is_some() {
-            errors.push(Error::unexpected_argument(line, DirectiveKind::Text));
-        }
+        let condition = Self::block_condition(line, argument, DirectiveKind::Text, &mut errors);
 
         if children.is_empty() {
             errors.push(Error::missing_text(line, DirectiveKind::Text));
         }
 
-        let mut paragraphs = Vec::with_capacity(children.len());
-
-        for child in children {
-            match child.kind {
-                BlockKind::Internal(_) => {
-                    let error = Error::unexpected_child_directive(child.line, DirectiveKind::Text);
-                    errors.push(error);
-                }
-                BlockKind::External(text) => {
-                    paragraphs.push(text);
-                }
-            }
-        }
+        let items = Item::parse_all(children, DirectiveKind::Text, &mut errors);
+        let paragraphs = Item::expand(&items, snippets, &mut errors);
 
         if errors.is_empty() {
-            Ok((line, PageBlock::Text(paragraphs)))
+            Ok((line, PageBlock::Text(paragraphs, condition)))
         } else {
             Err(errors)
         }
@@ -134,9 +194,13 @@ impl<'a> PageBlock<'a> {
     ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
         let mut errors = Vec::new();
 
-        if argument.is_none() {
-            errors.push(Error::missing_argument(line, DirectiveKind::Link));
-        }
+        let link = match argument {
+            Some(argument) => Some(Self::link_argument(line, argument, &mut errors)),
+            None => {
+                errors.push(Error::missing_argument(line, DirectiveKind::Link));
+                None
+            }
+        };
 
         let child = match children.as_slice() {
             [] => {
@@ -160,14 +224,274 @@ impl<'a> PageBlock<'a> {
             BlockKind::External(e) => e,
         };
 
-        if let Some(arg) = argument {
-            let link = PageBlock::Link(arg, text);
-            Ok((line, link))
+        match link {
+            Some(link) if errors.is_empty() => Ok((line, PageBlock::Link(Link { text, ..link }))),
+            _ => Err(errors),
+        }
+    }
+
+    /// Parse the argument of a link, which is its target followed by any modifiers, such as
+    /// `cave once`, `cave hidden-unless lamp` or `cave disabled "The door is locked"`, and lastly a
+    /// condition, such as `cave if first-visit`. The text of the link is left empty.
+    fn link_argument(line: usize, argument: &'a str, errors: &mut Vec<(usize, Error)>) -> Link<'a> {
+        const MODIFIERS: [&str; 4] = ["once", "hidden-unless", "disabled", "if"];
+
+        // Targets may contain spaces, so they run up to the first word which starts a modifier.
+        let start = argument.match_indices(' ').map(|(i, _)| i).find(|i| {
+            let word = argument[i + 1..].split(' ').next().unwrap_or_default();
+            MODIFIERS.contains(&word)
+        });
+        let (target, mut rest) = match start {
+            Some(i) => (argument[..i].trim_end(), &argument[i + 1..]),
+            None => (argument, ""),
+        };
+
+        let mut link = Link::new(target, "");
+
+        loop {
+            rest = rest.trim_start();
+            let (word, after) = rest.split_once(' ').unwrap_or((rest, ""));
+
+            match word {
+                "" => break,
+                // Conditions come last, as they may contain spaces.
+                "if" => {
+                    match Self::condition(after) {
+                        Some(condition) => link.condition = Some(condition),
+                        None => errors.push(Error::invalid_condition(line, after)),
+                    }
+                    break;
+                }
+                "once" => {
+                    link.once = true;
+                    rest = after;
+                }
+                "hidden-unless" => {
+                    let after = after.trim_start();
+                    let (flag, after) = after.split_once(' ').unwrap_or((after, ""));
+                    // A flag named like a modifier is more likely to be a missing flag.
+                    if flag.is_empty() || MODIFIERS.contains(&flag) {
+                        errors.push(Error::invalid_link_modifier(line, word));
+                        break;
+                    }
+
+                    link.hidden_unless = Some(flag);
+                    rest = after;
+                }
+                // The reason is quoted, as it may contain spaces.
+                "disabled" => {
+                    let quoted = after.trim_start().strip_prefix('"');
+                    match quoted.and_then(|quoted| quoted.split_once('"')) {
+                        Some((reason, after)) => {
+                            link.disabled = Some(reason);
+                            rest = after;
+                        }
+                        None => {
+                            errors.push(Error::invalid_link_modifier(line, rest));
+                            break;
+                        }
+                    }
+                }
+                _ => {
+                    errors.push(Error::invalid_link_modifier(line, word));
+                    break;
+                }
+            }
+        }
+
+        link
+    }
+
+    fn random(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+    ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
+        let mut errors = Vec::new();
+        let condition = Self::block_condition(line, argument, DirectiveKind::Random, &mut errors);
+
+        let mut texts = Vec::with_capacity(1);
+        let mut outcomes = Vec::new();
+
+        for child in children {
+            let (child_line, block) = match child.kind {
+                BlockKind::External(text) => {
+                    texts.push((child.line, text));
+                    continue;
+                }
+                BlockKind::Internal(block) if block.kind == DirectiveKind::Link => {
+                    (child.line, block)
+                }
+                BlockKind::Internal(_) => {
+                    let error =
+                        Error::unexpected_child_directive(child.line, DirectiveKind::Random);
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            // The target may be followed by a weight, such as `link goblin weight 3`.
+            let weighted = block
+                .argument
+                .and_then(|argument| argument.rsplit_once(" weight "));
+            let (argument, weight) = match weighted {
+                Some((argument, weight)) => match weight.trim().parse() {
+                    Ok(weight) if weight > 0 => (Some(argument.trim_end()), weight),
+                    _ => {
+                        errors.push(Error::invalid_weight(child_line, weight.trim()));
+                        continue;
+                    }
+                },
+                None => (block.argument, 1),
+            };
+
+            match Self::link(child_line, argument, block.children) {
+                Ok((_, PageBlock::Link(link))) if !link.is_modified() => {
+                    let Link { target, text, .. } = link;
+                    outcomes.push(Outcome {
+                        target,
+                        text,
+                        weight,
+                    });
+                }
+                Ok(_) => errors.push((child_line, Error::ConditionalOutcome)),
+                Err(new_errors) => errors.extend(new_errors),
+            }
+        }
+
+        let text = match texts.as_slice() {
+            [] => {
+                errors.push(Error::missing_text(line, DirectiveKind::Random));
+                ""
+            }
+            [(_, text)] => text,
+            [_, (second, _), ..] => {
+                errors.push(Error::excessive_child_count(*second, DirectiveKind::Random));
+                ""
+            }
+        };
+
+        if outcomes.is_empty() && errors.is_empty() {
+            errors.push((line, Error::MissingOutcomes));
+        }
+
+        if errors.is_empty() {
+            let random = Random {
+                text,
+                outcomes,
+                condition,
+            };
+            Ok((line, PageBlock::Random(random)))
         } else {
             Err(errors)
         }
     }
 
+    /// Parse the argument of a block which can only have a condition, such as `if first-visit`.
+    fn block_condition(
+        line: usize,
+        argument: Option<&'a str>,
+        kind: DirectiveKind,
+        errors: &mut Vec<(usize, Error)>,
+    ) -> Option<Condition<'a>> {
+        match argument.map(|a| a.strip_prefix("if ")) {
+            None => None,
+            Some(None) => {
+                errors.push(Error::unexpected_argument(line, kind));
+                None
+            }
+            Some(Some(condition)) => {
+                let parsed = Self::condition(condition);
+                if parsed.is_none() {
+                    errors.push(Error::invalid_condition(line, condition));
+                }
+                parsed
+            }
+        }
+    }
+
+    /// Parse a condition, which is `visited(page)` or `first-visit`, optionally preceded by `not`.
+    fn condition(condition: &'a str) -> Option<Condition<'a>> {
+        let condition = condition.trim();
+        let (negated, condition) = match condition.strip_prefix("not ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, condition),
+        };
+
+        if condition == "first-visit" {
+            return Some(if negated {
+                Condition::Revisit
+            } else {
+                Condition::FirstVisit
+            });
+        }
+
+        let page = condition
+            .strip_prefix("visited(")?
+            .strip_suffix(')')?
+            .trim();
+
+        match page {
+            "" => None,
+            page if negated => Some(Condition::NotVisited(page.into())),
+            page => Some(Condition::Visited(page.into())),
+        }
+    }
+
+    fn meta(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+    ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
+        let mut errors = Vec::new();
+
+        if !children.is_empty() {
+            errors.push(Error::unexpected_children(line, DirectiveKind::Meta));
+        }
+
+        // The first word of the argument is the key, and everything after it is the value.
+        let pair = match argument.map(|a| a.split_once(char::is_whitespace)) {
+            None => {
+                errors.push(Error::missing_argument(line, DirectiveKind::Meta));
+                None
+            }
+            Some(None) => {
+                errors.push(Error::missing_meta_value(
+                    line,
+                    argument.unwrap_or_default(),
+                ));
+                None
+            }
+            Some(Some((key, value))) => Some((key, value.trim_start())),
+        };
+
+        match pair {
+            Some((key, value)) if errors.is_empty() => Ok((line, PageBlock::Meta(key, value))),
+            _ => Err(errors),
+        }
+    }
+
+    fn template(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+    ) -> Result<(usize, PageBlock<'a>), Vec<(usize, Error)>> {
+        let mut errors = Vec::new();
+
+        if !children.is_empty() {
+            errors.push(Error::unexpected_children(line, DirectiveKind::Template));
+        }
+
+        match argument {
+            Some(name) if errors.is_empty() => Ok((line, PageBlock::Template(name))),
+            Some(_) => Err(errors),
+            None => {
+                errors.push(Error::missing_argument(line, DirectiveKind::Template));
+                Err(errors)
+            }
+        }
+    }
+
     fn title(
         line: usize,
         argument: Option<&str>,
@@ -204,15 +528,16 @@ impl<'a> PageBlock<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::script::Page;
+    use crate::script::{Condition, Link, Outcome, Page};
+    use std::borrow::Cow;
 
-    use super::{Block, DirectiveKind, Error, PageBlock};
+    use super::{Block, DirectiveKind, Error, PageBlock, Snippets};
 
     #[test]
     fn title_block_cannot_be_empty() {
         let input = Block::internal(2, DirectiveKind::Title, None, Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -224,7 +549,7 @@ mod tests {
     fn title_block_cannot_have_argument() {
         let input = Block::internal(2, DirectiveKind::Title, Some("oh no!"), Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(2, output.len());
         assert!(
@@ -244,7 +569,7 @@ mod tests {
             vec![Block::external(3, "oh no"), Block::external(4, "oh no 2")],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -261,7 +586,7 @@ mod tests {
             vec![Block::internal(3, DirectiveKind::Title, None, Vec::new())],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(matches!(
@@ -279,7 +604,7 @@ mod tests {
             vec![Block::external(3, "hurrah!")],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         assert!(matches!(output, (2, PageBlock::Title("hurrah!"))));
     }
@@ -288,7 +613,7 @@ mod tests {
     fn link_block_requires_argument_and_children() {
         let input = Block::internal(2, DirectiveKind::Link, None, Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(2, output.len());
         assert!(matches!(
@@ -309,7 +634,7 @@ mod tests {
     fn link_block_requires_children() {
         let input = Block::internal(2, DirectiveKind::Link, Some("go-to-this-page"), Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -326,7 +651,7 @@ mod tests {
             vec![Block::external(3, "oh no"), Block::external(5, "oh no 2")],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -343,7 +668,7 @@ mod tests {
             vec![Block::internal(12, DirectiveKind::Title, None, Vec::new())],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -360,13 +685,12 @@ mod tests {
             vec![Block::external(12, "Watch out for that landmine!")],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         assert!(matches!(
             output,
-            (10, PageBlock::Link(target, text))
-                if target == "trip-onto-landmine"
-                && text == "Watch out for that landmine!"
+            (10, PageBlock::Link(link))
+                if link == Link::new("trip-onto-landmine", "Watch out for that landmine!")
         ))
     }
 
@@ -374,7 +698,7 @@ mod tests {
     fn report_empty_text_blocks() {
         let input = Block::internal(123, DirectiveKind::Text, None, Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -391,7 +715,7 @@ mod tests {
             vec![Block::external(2000000000, "hello")],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(
@@ -413,11 +737,11 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         match output {
             (l, _) if l != 6 => panic!("Wrong line number!"),
-            (_, PageBlock::Text(paragraphs)) => {
+            (_, PageBlock::Text(paragraphs, None)) => {
                 assert_eq!(4, paragraphs.len());
                 assert_eq!("first paragraph", paragraphs[0]);
                 assert_eq!("the second", paragraphs[1]);
@@ -430,6 +754,236 @@ mod tests {
         }
     }
 
+    #[test]
+    fn can_parse_conditions() {
+        const EXAMPLES: &[(&str, Condition)] = &[
+            (
+                "if visited(cellar)",
+                Condition::Visited(Cow::Borrowed("cellar")),
+            ),
+            (
+                "if not visited( cellar )",
+                Condition::NotVisited(Cow::Borrowed("cellar")),
+            ),
+            ("if first-visit", Condition::FirstVisit),
+            ("if not first-visit", Condition::Revisit),
+        ];
+
+        for (argument, expected) in EXAMPLES {
+            let input = Block::internal(
+                6,
+                DirectiveKind::Text,
+                Some(argument),
+                vec![Block::external(7, "again")],
+            );
+
+            let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+            assert!(matches!(output, (6, PageBlock::Text(_, Some(c))) if c == *expected));
+        }
+
+        let input = Block::internal(
+            10,
+            DirectiveKind::Link,
+            Some("cellar if not visited(cellar)"),
+            vec![Block::external(11, "Go down.")],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+        assert!(matches!(
+            output,
+            (
+                10,
+                PageBlock::Link(Link {
+                    target,
+                    text: "Go down.",
+                    condition: Some(Condition::NotVisited(page)),
+                    ..
+                })
+            ) if target == "cellar" && page == "cellar"
+        ));
+    }
+
+    #[test]
+    fn can_parse_link_modifiers() {
+        let link = |argument| {
+            let children = vec![Block::external(1, "Go in.")];
+            let input = Block::internal(0, DirectiveKind::Link, Some(argument), children);
+            match PageBlock::parse(input, &Snippets::new()) {
+                Ok((_, PageBlock::Link(link))) => link,
+                other => panic!("Expected a link, found {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            Link {
+                once: true,
+                hidden_unless: Some("lamp"),
+                disabled: Some("The door is locked, if you must know."),
+                condition: Some(Condition::FirstVisit),
+                ..Link::new("the cave", "Go in.")
+            },
+            link("the cave once hidden-unless lamp disabled \"The door is locked, if you must know.\" if first-visit")
+        );
+        assert_eq!(
+            Link {
+                once: true,
+                ..Link::new("cave", "Go in.")
+            },
+            link("cave once")
+        );
+    }
+
+    #[test]
+    fn report_invalid_link_modifiers() {
+        for argument in [
+            "cave once twice",
+            "cave hidden-unless",
+            "cave hidden-unless if first-visit",
+            "cave disabled locked",
+        ] {
+            let children = vec![Block::external(4, "Go in.")];
+            let input = Block::internal(3, DirectiveKind::Link, Some(argument), children);
+            let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+            assert_eq!(1, output.len());
+            assert!(matches!(&output[0], (3, Error::InvalidLinkModifier { .. })));
+        }
+    }
+
+    #[test]
+    fn report_invalid_conditions() {
+        let input = Block::internal(
+            6,
+            DirectiveKind::Text,
+            Some("if visited()"),
+            vec![Block::external(7, "again")],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (6, Error::InvalidCondition { condition }) if condition == "visited()"
+        ));
+
+        let input = Block::internal(
+            10,
+            DirectiveKind::Link,
+            Some("cellar if sometimes"),
+            vec![Block::external(11, "Go down.")],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (10, Error::InvalidCondition { condition }) if condition == "sometimes"
+        ));
+    }
+
+    #[test]
+    fn can_parse_valid_random_block() {
+        let input = Block::internal(
+            3,
+            DirectiveKind::Random,
+            Some("if first-visit"),
+            vec![
+                Block::external(4, "Roll the dice."),
+                Block::internal(
+                    5,
+                    DirectiveKind::Link,
+                    Some("win weight 2"),
+                    vec![Block::external(6, "Six!")],
+                ),
+                Block::internal(
+                    7,
+                    DirectiveKind::Link,
+                    Some("lose"),
+                    vec![Block::external(8, "One.")],
+                ),
+            ],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+
+        match output {
+            (3, PageBlock::Random(random)) => {
+                assert_eq!("Roll the dice.", random.text);
+                assert_eq!(Some(Condition::FirstVisit), random.condition);
+                assert_eq!(
+                    vec![
+                        Outcome {
+                            target: "win".into(),
+                            text: "Six!",
+                            weight: 2
+                        },
+                        Outcome {
+                            target: "lose".into(),
+                            text: "One.",
+                            weight: 1
+                        },
+                    ],
+                    random.outcomes
+                );
+            }
+            _ => panic!("Incorrect PageBlock variant!"),
+        }
+    }
+
+    #[test]
+    fn report_invalid_random_blocks() {
+        let input = Block::internal(3, DirectiveKind::Random, None, Vec::new());
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (3, Error::MissingText { block }) if *block == DirectiveKind::Random
+        ));
+
+        let input = Block::internal(
+            3,
+            DirectiveKind::Random,
+            None,
+            vec![Block::external(4, "Roll the dice.")],
+        );
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(&output[0], (3, Error::MissingOutcomes)));
+
+        let input = Block::internal(
+            3,
+            DirectiveKind::Random,
+            None,
+            vec![
+                Block::external(4, "Roll the dice."),
+                Block::internal(
+                    5,
+                    DirectiveKind::Link,
+                    Some("win weight 0"),
+                    vec![Block::external(6, "Six!")],
+                ),
+                Block::internal(
+                    7,
+                    DirectiveKind::Link,
+                    Some("lose if first-visit"),
+                    vec![Block::external(8, "One.")],
+                ),
+            ],
+        );
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(2, output.len());
+        assert!(matches!(
+            &output[0],
+            (5, Error::InvalidWeight { weight }) if weight == "0"
+        ));
+        assert!(matches!(&output[1], (7, Error::ConditionalOutcome)));
+    }
+
     #[test]
     fn report_directives_within_text_block() {
         let input = Block::internal(
@@ -445,7 +999,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(2, output.len());
         assert!(matches!(
@@ -462,7 +1016,7 @@ mod tests {
     fn report_page_without_identifier() {
         let input = Block::internal(0, DirectiveKind::Page, None, Vec::new());
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(2, output.len());
         assert!(matches!(
@@ -484,7 +1038,7 @@ mod tests {
             Vec::new(),
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(matches!(
@@ -515,7 +1069,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(matches!(
@@ -538,7 +1092,7 @@ mod tests {
             )],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         match output {
             (line, _) if line != 50 => panic!("Line number is wrong"),
@@ -549,6 +1103,7 @@ mod tests {
                     title,
                     paragraphs,
                     links,
+                    ..
                 }),
             ) => {
                 assert_eq!("almost-empty", identifier);
@@ -592,7 +1147,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         match output {
             (line, _) if line != 0 => panic!("Line number is wrong"),
@@ -603,6 +1158,7 @@ mod tests {
                     title,
                     paragraphs,
                     links,
+                    ..
                 }),
             ) => {
                 assert_eq!("with-text", identifier);
@@ -628,7 +1184,7 @@ mod tests {
             vec![Block::internal(1, DirectiveKind::Title, None, Vec::new())],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert!(
             matches!(&output[0], (1, Error::MissingText { block }) if *block == DirectiveKind::Title)
@@ -663,7 +1219,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
 
         match output {
             (line, _) if line != 0 => panic!("Line number is wrong"),
@@ -674,13 +1230,14 @@ mod tests {
                     title,
                     paragraphs,
                     links,
+                    ..
                 }),
             ) => {
                 assert_eq!("with-links", identifier);
                 assert_eq!("Title 2", title);
                 assert_eq!(2, links.len());
-                assert_eq!(("page-three", "Go to page three"), links[0]);
-                assert_eq!(("page-seven", "Go to page seven"), links[1]);
+                assert_eq!(Link::new("page-three", "Go to page three"), links[0]);
+                assert_eq!(Link::new("page-seven", "Go to page seven"), links[1]);
                 assert!(paragraphs.is_empty());
             }
             _ => panic!("Incorrect PageBlock variant!"),
@@ -691,7 +1248,7 @@ mod tests {
     fn report_unexpected_text() {
         let input = Block::external(10, "Hello!");
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(matches!(&output[0], (10, Error::UnexpectedText)));
@@ -724,7 +1281,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
 
         assert_eq!(1, output.len());
         assert!(matches!(
@@ -758,7 +1315,7 @@ mod tests {
             ],
         );
 
-        let output = PageBlock::parse(input).unwrap_err();
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
         assert_eq!(1, output.len());
         assert!(matches!(
             &output[0],
@@ -770,4 +1327,186 @@ mod tests {
             )
         ))
     }
+
+    #[test]
+    fn can_parse_valid_meta_block() {
+        let input = Block::internal(
+            3,
+            DirectiveKind::Meta,
+            Some("mood  very spooky"),
+            Vec::new(),
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+
+        assert!(matches!(
+            output,
+            (3, PageBlock::Meta("mood", "very spooky"))
+        ));
+    }
+
+    #[test]
+    fn meta_block_requires_key_and_value() {
+        let input = Block::internal(3, DirectiveKind::Meta, None, Vec::new());
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (3, Error::MissingArgument { block }) if *block == DirectiveKind::Meta
+        ));
+
+        let input = Block::internal(4, DirectiveKind::Meta, Some("mood"), Vec::new());
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (4, Error::MissingMetaValue { key }) if key == "mood"
+        ));
+    }
+
+    #[test]
+    fn meta_block_cannot_have_children() {
+        let input = Block::internal(
+            3,
+            DirectiveKind::Meta,
+            Some("mood spooky"),
+            vec![Block::external(4, "oh no")],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (3, Error::UnexpectedChildren { block }) if *block == DirectiveKind::Meta
+        ));
+    }
+
+    #[test]
+    fn can_collect_meta_from_page() {
+        let input = Block::internal(
+            0,
+            DirectiveKind::Page,
+            Some("with-meta"),
+            vec![
+                Block::internal(
+                    1,
+                    DirectiveKind::Title,
+                    None,
+                    vec![Block::external(2, "Title")],
+                ),
+                Block::internal(3, DirectiveKind::Meta, Some("mood spooky"), Vec::new()),
+                Block::internal(4, DirectiveKind::Meta, Some("music organ.ogg"), Vec::new()),
+            ],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+
+        match output {
+            (_, PageBlock::Page(Page { meta, .. })) => {
+                assert_eq!(vec![("mood", "spooky"), ("music", "organ.ogg")], meta);
+            }
+            _ => panic!("Incorrect PageBlock variant!"),
+        }
+    }
+
+    #[test]
+    fn report_duplicate_meta_keys() {
+        let input = Block::internal(
+            0,
+            DirectiveKind::Page,
+            Some("with-meta"),
+            vec![
+                Block::internal(
+                    1,
+                    DirectiveKind::Title,
+                    None,
+                    vec![Block::external(2, "Title")],
+                ),
+                Block::internal(3, DirectiveKind::Meta, Some("mood spooky"), Vec::new()),
+                Block::internal(4, DirectiveKind::Meta, Some("mood cheery"), Vec::new()),
+            ],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (4, Error::DuplicateMetaKey { key }) if key == "mood"
+        ));
+    }
+
+    #[test]
+    fn template_block_requires_argument() {
+        let input = Block::internal(3, DirectiveKind::Template, None, Vec::new());
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (3, Error::MissingArgument { block }) if *block == DirectiveKind::Template
+        ));
+    }
+
+    #[test]
+    fn can_select_page_template() {
+        let input = Block::internal(
+            0,
+            DirectiveKind::Page,
+            Some("fight"),
+            vec![
+                Block::internal(
+                    1,
+                    DirectiveKind::Title,
+                    None,
+                    vec![Block::external(2, "Fight!")],
+                ),
+                Block::internal(3, DirectiveKind::Template, Some("combat"), Vec::new()),
+            ],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap();
+
+        assert!(matches!(
+            output,
+            (
+                _,
+                PageBlock::Page(Page {
+                    template: Some("combat"),
+                    ..
+                })
+            )
+        ));
+    }
+
+    #[test]
+    fn report_page_with_excessive_templates() {
+        let input = Block::internal(
+            0,
+            DirectiveKind::Page,
+            Some("fight"),
+            vec![
+                Block::internal(
+                    1,
+                    DirectiveKind::Title,
+                    None,
+                    vec![Block::external(2, "Fight!")],
+                ),
+                Block::internal(3, DirectiveKind::Template, Some("combat"), Vec::new()),
+                Block::internal(4, DirectiveKind::Template, Some("ending"), Vec::new()),
+            ],
+        );
+
+        let output = PageBlock::parse(input, &Snippets::new()).unwrap_err();
+
+        assert_eq!(1, output.len());
+        assert!(matches!(
+            &output[0],
+            (0, Error::ExcessivePageTemplates { page }) if page == "fight"
+        ));
+    }
 }
@@ -0,0 +1,594 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use super::{
+    block::{Block, BlockKind},
+    line::DirectiveKind,
+    Error,
+};
+
+/// Every snippet of a script, by name.
+pub type Snippets<'a> = HashMap<&'a str, Snippet<'a>>;
+
+/// The most lines a single text block can expand to. Snippets which use others several times can
+/// grow exponentially, so expansion stops here rather than running out of memory.
+const EXPANSION_LIMIT: usize = 10_000;
+
+/// A line of a snippet, or of a text block, which is either text or the use of another snippet.
+#[derive(Debug, PartialEq)]
+pub enum Item<'a> {
+    Text(&'a str),
+    Use(Use<'a>),
+}
+
+/// The use of a snippet, with the value of each of its parameters.
+#[derive(Debug, PartialEq)]
+pub struct Use<'a> {
+    pub line: usize,
+    pub name: &'a str,
+    pub arguments: Vec<(&'a str, &'a str)>,
+}
+
+/// Reusable lines of text, declared at the top level of a script, which text blocks can include
+/// with a `use` directive.
+#[derive(Debug, PartialEq)]
+pub struct Snippet<'a> {
+    pub line: usize,
+    pub parameters: Vec<&'a str>,
+    pub items: Vec<Item<'a>>,
+}
+
+impl<'a> Snippet<'a> {
+    /// Parse a snippet declaration, such as `snippet exits door window`, where the first word of the
+    /// argument is the name and the rest are parameters.
+    pub fn parse(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+    ) -> Result<(&'a str, Self), Vec<(usize, Error)>> {
+        let mut errors = Vec::new();
+
+        let mut words = argument.unwrap_or_default().split_whitespace();
+        let name = words.next().unwrap_or_else(|| {
+            errors.push(Error::missing_argument(line, DirectiveKind::Snippet));
+            "{unnamed}"
+        });
+        let parameters = words.collect();
+
+        if children.is_empty() {
+            errors.push(Error::missing_text(line, DirectiveKind::Snippet));
+        }
+
+        let items = Item::parse_all(children, DirectiveKind::Snippet, &mut errors);
+
+        if errors.is_empty() {
+            let snippet = Self {
+                line,
+                parameters,
+                items,
+            };
+            Ok((name, snippet))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl<'a> Item<'a> {
+    /// Parse the children of a block which can contain text and `use` directives.
+    pub fn parse_all(
+        children: Vec<Block<'a>>,
+        parent: DirectiveKind,
+        errors: &mut Vec<(usize, Error)>,
+    ) -> Vec<Self> {
+        let mut items = Vec::with_capacity(children.len());
+
+        for child in children {
+            match child.kind {
+                BlockKind::External(text) => items.push(Item::Text(text)),
+                BlockKind::Internal(block) if block.kind == DirectiveKind::Use => {
+                    match Use::parse(child.line, block.argument, block.children) {
+                        Ok(use_) => items.push(Item::Use(use_)),
+                        Err(new_errors) => errors.extend(new_errors),
+                    }
+                }
+                BlockKind::Internal(_) => {
+                    errors.push(Error::unexpected_child_directive(child.line, parent));
+                }
+            }
+        }
+
+        items
+    }
+
+    /// Expand items into lines of text, replacing each use of a snippet with its lines. Only the
+    /// uses among the items themselves are reported, since snippets are checked by [`validate`].
+    pub fn expand(
+        items: &[Item<'a>],
+        snippets: &Snippets<'a>,
+        errors: &mut Vec<(usize, Error)>,
+    ) -> Vec<Cow<'a, str>> {
+        let mut lines = Vec::new();
+        let mut stack = Vec::new();
+
+        for item in items {
+            let use_ = match item {
+                Item::Text(text) => {
+                    lines.push(Cow::Borrowed(*text));
+                    continue;
+                }
+                Item::Use(use_) => use_,
+            };
+
+            let snippet = match check_use(use_, snippets, errors) {
+                Some(snippet) => snippet,
+                None => continue,
+            };
+
+            let bindings = bind(use_, &[]);
+            stack.push(use_.name);
+            let expanded =
+                expand_items(&snippet.items, &bindings, snippets, &mut stack, &mut lines);
+            stack.pop();
+
+            if !expanded {
+                errors.push(Error::excessive_snippet_expansion(use_.line, use_.name));
+                break;
+            }
+        }
+
+        lines
+    }
+}
+
+/// Check every snippet once, whether or not it's used, reporting uses of snippets which don't
+/// exist, or with the wrong arguments, and snippets which end up using themselves.
+pub fn validate<'a>(snippets: &Snippets<'a>, errors: &mut Vec<(usize, Error)>) {
+    let mut ordered: Vec<_> = snippets.iter().collect();
+    ordered.sort_by_key(|(_, snippet)| snippet.line);
+
+    for (name, snippet) in ordered {
+        for item in &snippet.items {
+            let use_ = match item {
+                Item::Use(use_) => use_,
+                Item::Text(_) => continue,
+            };
+
+            if check_use(use_, snippets, errors).is_none() {
+                continue;
+            }
+
+            let mut visited = Vec::new();
+            if use_.name == *name || uses(snippets, use_.name, name, &mut visited) {
+                errors.push(Error::recursive_snippet(use_.line, use_.name));
+            }
+        }
+    }
+}
+
+/// Whether a snippet uses another, directly or through other snippets. Snippets which have already
+/// been visited are skipped, so each is only searched once.
+fn uses<'a>(snippets: &Snippets<'a>, from: &'a str, to: &str, visited: &mut Vec<&'a str>) -> bool {
+    if visited.contains(&from) {
+        return false;
+    }
+    visited.push(from);
+
+    let items = snippets.get(from).map(|snippet| snippet.items.as_slice());
+    items.unwrap_or_default().iter().any(|item| match item {
+        Item::Use(use_) => use_.name == to || uses(snippets, use_.name, to, visited),
+        Item::Text(_) => false,
+    })
+}
+
+/// Find the snippet a `use` directive names, reporting it if it doesn't exist, or if the arguments
+/// don't match its parameters.
+fn check_use<'a, 's>(
+    use_: &Use<'a>,
+    snippets: &'s Snippets<'a>,
+    errors: &mut Vec<(usize, Error)>,
+) -> Option<&'s Snippet<'a>> {
+    let snippet = match snippets.get(use_.name) {
+        Some(snippet) => snippet,
+        None => {
+            errors.push(Error::unknown_snippet(use_.line, use_.name));
+            return None;
+        }
+    };
+
+    let mut failed = false;
+    for (parameter, _) in &use_.arguments {
+        if !snippet.parameters.contains(parameter) {
+            errors.push(Error::unknown_snippet_parameter(
+                use_.line, use_.name, parameter,
+            ));
+            failed = true;
+        }
+    }
+
+    for parameter in &snippet.parameters {
+        if !use_.arguments.iter().any(|(name, _)| name == parameter) {
+            errors.push(Error::missing_snippet_argument(
+                use_.line, use_.name, parameter,
+            ));
+            failed = true;
+        }
+    }
+
+    (!failed).then_some(snippet)
+}
+
+/// The value of each parameter of a snippet for a use of it. Values can refer to the parameters of
+/// the snippet this one is used within.
+fn bind<'a>(use_: &Use<'a>, bindings: &[(&'a str, Cow<'a, str>)]) -> Vec<(&'a str, Cow<'a, str>)> {
+    let values = use_.arguments.iter();
+    values
+        .map(|(parameter, value)| (*parameter, substitute(value, bindings)))
+        .collect()
+}
+
+impl<'a> Use<'a> {
+    /// Parse a `use` directive, naming a snippet. Each child gives the value of a parameter, with the
+    /// first word as the name and the rest as the value, such as `door the oak door`.
+    fn parse(
+        line: usize,
+        argument: Option<&'a str>,
+        children: Vec<Block<'a>>,
+    ) -> Result<Self, Vec<(usize, Error)>> {
+        let mut errors = Vec::new();
+        let mut arguments = Vec::with_capacity(children.len());
+
+        for child in children {
+            match child.kind {
+                BlockKind::External(text) => {
+                    let (parameter, value) =
+                        text.split_once(char::is_whitespace).unwrap_or((text, ""));
+                    arguments.push((parameter, value.trim_start()));
+                }
+                // Parameters named like directives, such as `text`, are parsed as directives.
+                BlockKind::Internal(block) if block.children.is_empty() => {
+                    let parameter = block.kind.keyword();
+                    arguments.push((parameter, block.argument.unwrap_or_default()));
+                }
+                BlockKind::Internal(_) => {
+                    let error = Error::unexpected_child_directive(child.line, DirectiveKind::Use);
+                    errors.push(error);
+                }
+            }
+        }
+
+        match argument {
+            Some(name) if errors.is_empty() => Ok(Self {
+                line,
+                name,
+                arguments,
+            }),
+            Some(_) => Err(errors),
+            None => {
+                errors.push(Error::missing_argument(line, DirectiveKind::Use));
+                Err(errors)
+            }
+        }
+    }
+}
+
+/// Expand the items of a snippet with the given values of its parameters. Uses which
+/// [`validate`] reports are left out. The stack holds the names of the snippets being expanded,
+/// so a snippet which uses itself isn't expanded forever. Returns whether the lines stayed within
+/// the limit.
+fn expand_items<'a>(
+    items: &[Item<'a>],
+    bindings: &[(&'a str, Cow<'a, str>)],
+    snippets: &Snippets<'a>,
+    stack: &mut Vec<&'a str>,
+    lines: &mut Vec<Cow<'a, str>>,
+) -> bool {
+    for item in items {
+        if lines.len() >= EXPANSION_LIMIT {
+            return false;
+        }
+
+        let use_ = match item {
+            Item::Text(text) => {
+                lines.push(substitute(text, bindings));
+                continue;
+            }
+            Item::Use(use_) => use_,
+        };
+
+        // Problems with uses within snippets have already been reported.
+        let snippet = match check_use(use_, snippets, &mut Vec::new()) {
+            Some(snippet) if !stack.contains(&use_.name) => snippet,
+            _ => continue,
+        };
+
+        let inner = bind(use_, bindings);
+        stack.push(use_.name);
+        let expanded = expand_items(&snippet.items, &inner, snippets, stack, lines);
+        stack.pop();
+
+        if !expanded {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Replace each `{parameter}` in some text with its value. Anything else in braces is left as it
+/// is, and values are never substituted themselves.
+fn substitute<'a>(text: &'a str, bindings: &[(&'a str, Cow<'a, str>)]) -> Cow<'a, str> {
+    if bindings.is_empty() || !text.contains('{') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        let (before, after) = rest.split_at(start);
+        output.push_str(before);
+
+        let value = after[1..].find('}').and_then(|end| {
+            let name = &after[1..=end];
+            let value = bindings.iter().find(|(parameter, _)| *parameter == name)?;
+            Some((end, &value.1))
+        });
+
+        match value {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push('{');
+                rest = &after[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{substitute, Item, Snippet, Snippets, EXPANSION_LIMIT};
+    use crate::script::{
+        parse::{block::BlockKind, string_to_lines, Block, DirectiveKind, Error},
+        Script,
+    };
+    use std::borrow::Cow;
+
+    fn snippets(input: &str) -> Snippets<'_> {
+        let lines = string_to_lines(input, None).unwrap();
+        let blocks = Block::parse(lines).unwrap();
+
+        blocks
+            .into_iter()
+            .map(|block| match block.kind {
+                BlockKind::Internal(internal) => {
+                    Snippet::parse(block.line, internal.argument, internal.children).unwrap()
+                }
+                _ => panic!("Expected a snippet!"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn can_substitute_parameters() {
+        let bindings = [
+            ("door", Cow::Borrowed("oak")),
+            ("x", Cow::Borrowed("{door}")),
+        ];
+
+        assert_eq!("An oak door.", substitute("An {door} door.", &bindings));
+        assert_eq!("{door} and {y", substitute("{x} and {y", &bindings));
+        assert_eq!("{} {unknown}", substitute("{} {unknown}", &bindings));
+    }
+
+    #[test]
+    fn can_expand_snippets() {
+        let snippets = snippets(
+            "snippet exits way
+    Exits lead {way}.
+    use sign
+        text Keep {way}!
+
+snippet sign text
+    A sign reads \"{text}\".
+",
+        );
+
+        let items = [
+            Item::Text("A room."),
+            Item::Use(super::Use {
+                line: 10,
+                name: "exits",
+                arguments: vec![("way", "north")],
+            }),
+        ];
+
+        let mut errors = Vec::new();
+        let lines = Item::expand(&items, &snippets, &mut errors);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            vec![
+                "A room.",
+                "Exits lead north.",
+                "A sign reads \"Keep north!\"."
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn report_bad_uses() {
+        let snippets = snippets(
+            "snippet loop
+    use loop
+
+snippet greet name
+    Hello, {name}.
+",
+        );
+
+        let use_ = |line, name, arguments| {
+            Item::Use(super::Use {
+                line,
+                name,
+                arguments,
+            })
+        };
+        let items = [
+            use_(10, "loop", vec![]),
+            use_(11, "missing", vec![]),
+            use_(12, "greet", vec![]),
+            use_(13, "greet", vec![("name", "you"), ("mood", "happy")]),
+        ];
+
+        let mut errors = Vec::new();
+        super::validate(&snippets, &mut errors);
+        let lines = Item::expand(&items, &snippets, &mut errors);
+
+        assert!(lines.is_empty());
+        assert_eq!(4, errors.len());
+        assert!(matches!(
+            &errors[0],
+            (1, Error::RecursiveSnippet { name }) if name == "loop"
+        ));
+        assert!(matches!(
+            &errors[1],
+            (11, Error::UnknownSnippet { name }) if name == "missing"
+        ));
+        assert!(matches!(
+            &errors[2],
+            (12, Error::MissingSnippetArgument { snippet, parameter })
+                if snippet == "greet" && parameter == "name"
+        ));
+        assert!(matches!(
+            &errors[3],
+            (13, Error::UnknownSnippetParameter { snippet, parameter })
+                if snippet == "greet" && parameter == "mood"
+        ));
+    }
+
+    #[test]
+    fn report_problems_within_snippets_once() {
+        let errors = Script::new(
+            "snippet unused
+    use missing
+
+snippet a
+    use b
+
+snippet b
+    use a
+
+page hall
+    title
+        Hall
+    text
+        use a
+        use a
+",
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(3, errors.len());
+        assert!(matches!(
+            &errors[0],
+            (1, Error::UnknownSnippet { name }) if name == "missing"
+        ));
+        assert!(matches!(
+            &errors[1],
+            (4, Error::RecursiveSnippet { name }) if name == "b"
+        ));
+        assert!(matches!(
+            &errors[2],
+            (7, Error::RecursiveSnippet { name }) if name == "a"
+        ));
+    }
+
+    #[test]
+    fn report_excessive_expansion() {
+        let mut script = String::from("snippet s0\n    Boom.\n");
+        for n in 1..40 {
+            let previous = n - 1;
+            script.push_str(&format!(
+                "snippet s{n}\n    use s{previous}\n    use s{previous}\n"
+            ));
+        }
+        let snippets = snippets(&script);
+
+        let items = [Item::Use(super::Use {
+            line: 100,
+            name: "s39",
+            arguments: vec![],
+        })];
+
+        let mut errors = Vec::new();
+        let lines = Item::expand(&items, &snippets, &mut errors);
+
+        assert_eq!(EXPANSION_LIMIT, lines.len());
+        assert_eq!(1, errors.len());
+        assert!(matches!(
+            &errors[0],
+            (100, Error::ExcessiveSnippetExpansion { name }) if name == "s39"
+        ));
+    }
+
+    #[test]
+    fn can_use_snippets_in_scripts() {
+        let script = Script::new(
+            "page hall
+    title
+        Hall
+    text
+        A grand hall.
+        use exits
+            way south
+
+snippet exits way
+    The only way out is {way}.
+",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["A grand hall.", "The only way out is south."],
+            script.pages[0].paragraphs
+        );
+    }
+
+    #[test]
+    fn report_misplaced_and_duplicate_snippets() {
+        let errors = Script::new(
+            "snippet a
+    One.
+
+snippet a
+    Two.
+
+page hall
+    title
+        Hall
+    use a
+",
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(2, errors.len());
+        assert!(matches!(
+            &errors[0],
+            (3, Error::DuplicateSnippet { name }) if name == "a"
+        ));
+        assert!(matches!(
+            &errors[1],
+            (9, Error::MisplacedDirective { block }) if *block == DirectiveKind::Use
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+use std::iter::Peekable;
+
+use super::{
+    line::{DirectiveKind, Indentation, Line, LineKind},
+    Error,
+};
+
+/// A single line of a script, split into its parts without discarding anything. Writing each part
+/// out in order gives back the exact original line.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxLine<'a> {
+    /// The (zero-based) number of the line within the script.
+    pub number: usize,
+
+    /// Whitespace before the content of the line.
+    pub indent: &'a str,
+
+    /// The meaningful part of the line, a directive or text. Empty for blank and comment lines.
+    pub content: &'a str,
+
+    /// Whitespace and comments following the content.
+    pub trailing: &'a str,
+
+    /// The line terminator, either `"\n"`, `"\r\n"`, a lone `"\r"` ending the last line, or empty
+    /// if the last line has none.
+    pub ending: &'a str,
+}
+
+impl<'a> SyntaxLine<'a> {
+    /// Split a raw line (including its terminator) into its parts, using the already parsed line to
+    /// decide where the content ends.
+    fn split(number: usize, raw: &'a str, kind: Option<&LineKind<'a>>) -> Self {
+        // Strip the terminator in the same way as the parser, so a lone `\r` is an ending too.
+        let body = raw.strip_suffix('\n').unwrap_or(raw);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        let ending = &raw[body.len()..];
+
+        let start = match kind {
+            Some(LineKind::Text(text)) => body.len() - text.len(),
+            _ => body.len() - body.trim_start().len(),
+        };
+        let (indent, rest) = body.split_at(start);
+
+        let (content, trailing) = match kind {
+            // Blank and comment lines have no content at all.
+            None => ("", rest),
+            // Text lines keep everything, comment characters included.
+            Some(LineKind::Text(_)) => (rest, ""),
+            // Meta values can contain `#`, so run to the end of the line.
+            Some(LineKind::Directive(DirectiveKind::Meta, _)) => {
+                rest.split_at(rest.trim_end().len())
+            }
+            Some(LineKind::Directive(..)) => {
+                let before_comment = rest.split_once('#').map_or(rest, |(before, _)| before);
+                rest.split_at(before_comment.trim_end().len())
+            }
+        };
+
+        Self {
+            number,
+            indent,
+            content,
+            trailing,
+            ending,
+        }
+    }
+
+    /// Return the comment on this line (starting at the `#`), if any.
+    pub fn comment(&self) -> Option<&'a str> {
+        self.trailing.find('#').map(|index| &self.trailing[index..])
+    }
+}
+
+impl<'a> std::fmt::Display for SyntaxLine<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}",
+            self.indent, self.content, self.trailing, self.ending
+        )
+    }
+}
+
+/// A meaningful line of a script, along with the lines nested beneath it.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxNode<'a> {
+    /// Blank and comment lines directly preceding this one.
+    pub leading: Vec<SyntaxLine<'a>>,
+
+    /// The raw parts of this line.
+    pub syntax: SyntaxLine<'a>,
+
+    /// The parsed form of this line.
+    pub line: Line<'a>,
+
+    /// Nodes indented beneath this one.
+    pub children: Vec<SyntaxNode<'a>>,
+}
+
+impl<'a> std::fmt::Display for SyntaxNode<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.leading {
+            write!(f, "{line}")?;
+        }
+
+        write!(f, "{}", self.syntax)?;
+
+        for child in &self.children {
+            write!(f, "{child}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A lossless concrete syntax tree of a script. Unlike [`super::block::Block`], this keeps comments
+/// and blank lines, so it can be written back out to the exact original text.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxTree<'a> {
+    /// The top-level nodes of the script.
+    pub nodes: Vec<SyntaxNode<'a>>,
+
+    /// Blank and comment lines following the last node.
+    pub trailing: Vec<SyntaxLine<'a>>,
+}
+
+impl<'a> SyntaxTree<'a> {
+    pub fn parse(
+        input: &'a str,
+        mut indentation: Option<Indentation>,
+    ) -> Result<Self, Vec<(usize, Error)>> {
+        let mut items = Vec::new();
+        let mut pending = Vec::new();
+        let mut errors = Vec::new();
+
+        for (number, raw) in input.split_inclusive('\n').enumerate() {
+            let body = raw.strip_suffix('\n').unwrap_or(raw);
+            let body = body.strip_suffix('\r').unwrap_or(body);
+
+            if indentation.is_none() {
+                match Indentation::detect(body) {
+                    Ok(detected) => indentation = detected,
+                    Err(e) => {
+                        errors.push((number, e));
+                        continue;
+                    }
+                }
+            }
+
+            match Line::parse(body, indentation.unwrap_or_default()) {
+                Ok(Some(line)) => {
+                    let syntax = SyntaxLine::split(number, raw, Some(&line.kind));
+                    let leading = std::mem::take(&mut pending);
+                    items.push((leading, syntax, line));
+                }
+                Ok(None) => pending.push(SyntaxLine::split(number, raw, None)),
+                Err(e) => errors.push((number, e)),
+            }
+        }
+
+        let mut items = items.into_iter().peekable();
+        let nodes = Self::parse_indented(0, &mut items, &mut errors);
+
+        if errors.is_empty() {
+            Ok(Self {
+                nodes,
+                trailing: pending,
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Nest lines by indentation, in the same way as [`super::block::Block::parse`].
+    fn parse_indented<I>(
+        indent: usize,
+        items: &mut Peekable<I>,
+        errors: &mut Vec<(usize, Error)>,
+    ) -> Vec<SyntaxNode<'a>>
+    where
+        I: Iterator<Item = (Vec<SyntaxLine<'a>>, SyntaxLine<'a>, Line<'a>)>,
+    {
+        let mut nodes = Vec::new();
+
+        while let Some((leading, syntax, line)) =
+            items.next_if(|(_, _, line)| line.indent >= indent)
+        {
+            if line.indent > indent {
+                errors.push((
+                    syntax.number,
+                    Error::UnexpectedIndenation {
+                        expected: indent,
+                        found: line.indent,
+                    },
+                ));
+
+                while items
+                    .next_if(|(_, _, line)| line.indent >= indent)
+                    .is_some()
+                {}
+                return Vec::new();
+            }
+
+            let children = match line.kind {
+                LineKind::Text(_) => Vec::new(),
+                LineKind::Directive(..) => Self::parse_indented(indent + 1, items, errors),
+            };
+
+            nodes.push(SyntaxNode {
+                leading,
+                syntax,
+                line,
+                children,
+            });
+        }
+
+        nodes
+    }
+}
+
+impl<'a> std::fmt::Display for SyntaxTree<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{node}")?;
+        }
+
+        for line in &self.trailing {
+            write!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{line::DirectiveKind, Error},
+        Indentation, Line, SyntaxTree,
+    };
+
+    #[test]
+    fn can_round_trip_scripts() {
+        const EXAMPLES: &[&str] = &[
+            "",
+            "\n\n\n",
+            "# Only a comment",
+            "page start # The first page.\n    title\n        Start\n",
+            "page start\r\n    title   \r\n        Start  \r\n\r\n",
+            "  # A badly indented comment\npage a\n\n    # Comment\n    text\n        Para # 1\n\n\n",
+            "page no-trailing-newline\n    title\n        The end",
+            "page tabs\t# Tabbed.\n\ttitle\n\t\tTabs\n",
+            "page meta\n    meta colour #ff0000  \n",
+            "page a\n    title\n        A\npage b\n    title\n        B\n# Fin.\n   \n",
+        ];
+
+        for input in EXAMPLES {
+            let tree = SyntaxTree::parse(input, None).unwrap();
+            assert_eq!(*input, tree.to_string());
+        }
+    }
+
+    #[test]
+    fn can_keep_comments_and_blank_lines() {
+        let input = "# Header comment
+
+page start   # The first page.
+    title
+        Start # Not a comment, since it's text.
+
+# Footer
+";
+
+        let tree = SyntaxTree::parse(input, None).unwrap();
+
+        assert_eq!(1, tree.nodes.len());
+        let page = &tree.nodes[0];
+
+        assert_eq!(2, page.leading.len());
+        assert_eq!(Some("# Header comment"), page.leading[0].comment());
+        assert_eq!("", page.leading[1].content);
+
+        assert_eq!("page start", page.syntax.content);
+        assert_eq!("   # The first page.", page.syntax.trailing);
+        assert_eq!(
+            Line::new_directive(0, DirectiveKind::Page, Some("start")),
+            page.line
+        );
+
+        let title = &page.children[0];
+        let text = &title.children[0];
+        assert_eq!(
+            "Start # Not a comment, since it's text.",
+            text.syntax.content
+        );
+        assert_eq!(None, text.syntax.comment());
+
+        let meta = SyntaxTree::parse("meta colour #ff0000\n", None).unwrap();
+        assert_eq!("meta colour #ff0000", meta.nodes[0].syntax.content);
+        assert_eq!(None, meta.nodes[0].syntax.comment());
+
+        assert_eq!(2, tree.trailing.len());
+        assert_eq!("", tree.trailing[0].content);
+        assert_eq!(Some("# Footer"), tree.trailing[1].comment());
+    }
+
+    #[test]
+    fn line_numbers_match_source() {
+        let input = "page a\n\n    title\r\n\n        A\n";
+
+        let tree = SyntaxTree::parse(input, None).unwrap();
+
+        let page = &tree.nodes[0];
+        let title = &page.children[0];
+        let text = &title.children[0];
+
+        assert_eq!(0, page.syntax.number);
+        assert_eq!(2, title.syntax.number);
+        assert_eq!(1, title.leading[0].number);
+        assert_eq!("\r\n", title.syntax.ending);
+        assert_eq!(4, text.syntax.number);
+    }
+
+    #[test]
+    fn can_end_with_carriage_return() {
+        let input = "page a\r\n    title\r\n        A\r";
+
+        let tree = SyntaxTree::parse(input, None).unwrap();
+
+        let text = &tree.nodes[0].children[0].children[0];
+        assert_eq!("        ", text.syntax.indent);
+        assert_eq!("A", text.syntax.content);
+        assert_eq!("\r", text.syntax.ending);
+        assert_eq!(input, tree.to_string());
+    }
+
+    #[test]
+    fn can_report_errors() {
+        let input = "page a\n        title\n   bad\n";
+
+        let errors = SyntaxTree::parse(input, Some(Indentation::Spaces(4))).unwrap_err();
+
+        assert_eq!(2, errors.len());
+        assert!(matches!(
+            errors[0],
+            (2, Error::InvalidIndentation { count: 3, .. })
+        ));
+        assert!(matches!(
+            errors[1],
+            (
+                1,
+                Error::UnexpectedIndenation {
+                    expected: 1,
+                    found: 2
+                }
+            )
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+//! Section numbers for gamebooks, which are shuffled so readers can't just read straight through.
+
+/// A small pseudo-random number generator (SplitMix64). It only needs to be deterministic, so the
+/// same seed always gives the same book.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Pick a number below the given bound.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Assign a section number to each of a number of pages, in script order. Without a seed, sections
+/// follow the script. With one, they're shuffled, but the first page is always section 1.
+pub fn numbers(count: usize, seed: Option<u64>) -> Vec<usize> {
+    let mut numbers: Vec<_> = (1..=count).collect();
+
+    if let Some(seed) = seed {
+        let mut random = Random(seed);
+
+        // Fisher-Yates shuffle, leaving the first page where it is.
+        for index in (2..count).rev() {
+            let other = 1 + random.below(index);
+            numbers.swap(index, other);
+        }
+    }
+
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::numbers;
+
+    #[test]
+    fn can_keep_script_order() {
+        assert_eq!(vec![1, 2, 3, 4], numbers(4, None));
+        assert_eq!(Vec::<usize>::new(), numbers(0, Some(1)));
+    }
+
+    #[test]
+    fn can_shuffle_sections() {
+        let shuffled = numbers(50, Some(42));
+
+        assert_eq!(1, shuffled[0]);
+        assert_eq!(shuffled, numbers(50, Some(42)));
+        assert_ne!(shuffled, numbers(50, None));
+        assert_ne!(shuffled, numbers(50, Some(43)));
+
+        let mut sorted = shuffled.clone();
+        sorted.sort_unstable();
+        assert_eq!(numbers(50, None), sorted);
+    }
+}
@@ -0,0 +1,236 @@
+use super::{escape, Warning};
+use crate::adventure::{Adventure, Story};
+use std::fmt::Write;
+
+/// Write an adventure as a Twee 3 story, with a passage for each page named by its identifier.
+/// Anything which Twee can't represent is left out and reported.
+pub fn export(adventure: &Adventure, story: &Story) -> (String, Vec<Warning>) {
+    let mut output = String::new();
+    let mut warnings = Vec::new();
+
+    if let Some(title) = &story.info.title {
+        let _ = write!(output, ":: StoryTitle\n{title}\n\n");
+    }
+
+    let mut data = serde_json::Map::new();
+    data.insert("ifid".to_owned(), story.identifier.as_str().into());
+    if let Some(start) = adventure.pages.first() {
+        data.insert("start".to_owned(), start.identifier.into());
+    }
+    let data = serde_json::to_string_pretty(&data).unwrap_or_default();
+    let _ = write!(output, ":: StoryData\n{data}\n");
+
+    for page in &adventure.pages {
+        let page_name = page.identifier.to_owned();
+
+        if page.title != page.identifier {
+            warnings.push(Warning::DroppedTitle {
+                page: page_name.clone(),
+            });
+        }
+
+        for key in page.meta.keys() {
+            warnings.push(Warning::DroppedMeta {
+                page: page_name.clone(),
+                key: key.to_string(),
+            });
+        }
+
+        let _ = write!(output, "\n:: {}", escape(page.identifier));
+
+        match page.template {
+            Some(template) if template.contains(char::is_whitespace) => {
+                warnings.push(Warning::DroppedTemplate {
+                    page: page_name.clone(),
+                    template: template.to_owned(),
+                });
+            }
+            Some(template) => {
+                let _ = write!(output, " [{}]", escape(template));
+            }
+            None => {}
+        }
+        output.push('\n');
+
+        // Conditions need the macros of a story format, so conditional text and links are left out
+        // rather than always shown.
+        for paragraph in &page.text {
+            match &paragraph.condition {
+                Some(condition) => warnings.push(Warning::DroppedCondition {
+                    page: page_name.clone(),
+                    condition: condition.to_string(),
+                }),
+                None => {
+                    write_text(&mut output, paragraph.text);
+                    output.push('\n');
+                }
+            }
+        }
+
+        for link in &page.links {
+            if let Some(condition) = &link.condition {
+                warnings.push(Warning::DroppedCondition {
+                    page: page_name.clone(),
+                    condition: condition.to_string(),
+                });
+                continue;
+            }
+
+            // Page indices start from 1.
+            let target = adventure.pages[link.index - 1].identifier;
+
+            let modifiers = [
+                ("once", link.once),
+                ("hidden-unless", link.hidden_unless.is_some()),
+            ];
+            for (modifier, _) in modifiers.iter().filter(|(_, set)| *set) {
+                warnings.push(Warning::DroppedLinkModifier {
+                    page: page_name.clone(),
+                    text: link.text.to_owned(),
+                    modifier,
+                });
+            }
+
+            // Disabled links can't be followed, so they give their reason instead.
+            if let Some(reason) = link.disabled {
+                write_text(&mut output, &format!("{} ({reason})", link.text));
+            } else if can_link(link.text, target) {
+                let _ = writeln!(output, "[[{}->{target}]]", link.text);
+            } else {
+                warnings.push(Warning::DroppedLink {
+                    page: page_name.clone(),
+                    text: link.text.to_owned(),
+                });
+            }
+        }
+
+        for choice in &page.random {
+            warnings.push(Warning::DroppedRandom {
+                page: page_name.clone(),
+                text: choice.text.to_owned(),
+            });
+        }
+    }
+
+    (output, warnings)
+}
+
+/// Write a line of text, escaping any line which Twee would read as a passage header.
+fn write_text(output: &mut String, text: &str) {
+    for line in text.lines() {
+        if line.starts_with("::") {
+            output.push('\\');
+        }
+        let _ = writeln!(output, "{line}");
+    }
+}
+
+/// Check whether a link can be written without its text or target being misread. Story formats
+/// split `[[text->target]]` at the last arrow, so only the text may contain one.
+fn can_link(text: &str, target: &str) -> bool {
+    let separators = ["]]", "|", "<-"];
+    let in_text = separators.iter().any(|s| text.contains(s));
+    let in_target = separators.iter().chain(&["->"]).any(|s| target.contains(s));
+    !(in_text || in_target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, Warning};
+    use crate::{
+        adventure::{Adventure, Story},
+        config,
+        script::Script,
+    };
+
+    #[test]
+    fn can_export_adventure() {
+        let script = Script::new(
+            "page start
+    title
+        start
+    template dark
+    text
+        You wake up.
+
+        :: It's cold.
+    link cellar
+        Go -> down.
+    link start
+        Stay | here.
+    link cellar disabled \"Too dark\"
+        Climb down.
+    link cellar once
+        Jump down.
+    text if not first-visit
+        You're back.
+    link cellar if visited(cellar)
+        Go down again.
+
+page cellar
+    title
+        The Cellar
+    meta music drip.ogg
+",
+            None,
+        )
+        .unwrap();
+        let adventure = Adventure::new(&script).unwrap();
+        let info = config::Story {
+            title: Some("The Cold".to_owned()),
+            ..config::Story::default()
+        };
+        let story = Story::new(&info, &adventure, 0);
+
+        let (output, warnings) = export(&adventure, &story);
+
+        let (header, passages) = output.split_once("\n\n:: start").unwrap();
+        assert!(header.starts_with(":: StoryTitle\nThe Cold\n\n:: StoryData\n"));
+        assert!(header.contains("\"start\": \"start\""));
+
+        assert_eq!(
+            " [dark]
+You wake up.
+
+\\:: It's cold.
+
+[[Go -> down.->cellar]]
+Climb down. (Too dark)
+[[Jump down.->cellar]]
+
+:: cellar
+",
+            passages
+        );
+
+        assert_eq!(
+            vec![
+                Warning::DroppedCondition {
+                    page: "start".to_owned(),
+                    condition: "not first-visit".to_owned()
+                },
+                Warning::DroppedLink {
+                    page: "start".to_owned(),
+                    text: "Stay | here.".to_owned()
+                },
+                Warning::DroppedLinkModifier {
+                    page: "start".to_owned(),
+                    text: "Jump down.".to_owned(),
+                    modifier: "once"
+                },
+                Warning::DroppedCondition {
+                    page: "start".to_owned(),
+                    condition: "visited(cellar)".to_owned()
+                },
+                Warning::DroppedTitle {
+                    page: "cellar".to_owned()
+                },
+                Warning::DroppedMeta {
+                    page: "cellar".to_owned(),
+                    key: "music".to_owned()
+                },
+            ],
+            warnings
+        );
+    }
+}
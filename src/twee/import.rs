@@ -0,0 +1,444 @@
+use super::Warning;
+use crate::script::parse::DirectiveKind;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+/// A passage of a Twee story.
+#[derive(Debug, PartialEq)]
+struct Passage<'a> {
+    name: String,
+    tags: Vec<&'a str>,
+    text: String,
+}
+
+/// Split a Twee story into its passages. Anything before the first passage is ignored.
+fn passages(input: &str) -> Vec<Passage<'_>> {
+    let mut passages: Vec<Passage> = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in input.lines() {
+        if let Some(header) = line.strip_prefix("::") {
+            if let Some(passage) = passages.last_mut() {
+                passage.text = lines.join("\n").trim().to_owned();
+            }
+            lines.clear();
+            passages.push(header_to_passage(header));
+        } else {
+            // Lines of text starting with `::` are escaped, so aren't read as headers.
+            lines.push(
+                line.strip_prefix('\\')
+                    .filter(|l| l.starts_with("::"))
+                    .unwrap_or(line),
+            );
+        }
+    }
+
+    if let Some(passage) = passages.last_mut() {
+        passage.text = lines.join("\n").trim().to_owned();
+    }
+
+    passages
+}
+
+/// Read the name and tags from a passage header, such as `:: Name [tag1 tag2] {"position":"0,0"}`.
+/// The metadata is of no use to us, so is ignored.
+fn header_to_passage(header: &str) -> Passage<'_> {
+    let mut name = String::new();
+    let mut chars = header.char_indices();
+    let mut rest = "";
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next().map(|(_, c)| c)),
+            '[' | '{' => {
+                rest = &header[index..];
+                break;
+            }
+            c => name.push(c),
+        }
+    }
+
+    let tags = rest
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(tags, _)| tags.split_whitespace().collect())
+        .unwrap_or_default();
+
+    Passage {
+        name: name.trim().to_owned(),
+        tags,
+        text: String::new(),
+    }
+}
+
+/// Read a link, such as `[[text->target]]`, `[[target<-text]]`, `[[text|target]]` or `[[target]]`,
+/// given what's between the brackets. Return the text and target.
+fn link(inner: &str) -> (&str, &str) {
+    if let Some((text, target)) = inner.split_once('|') {
+        (text, target)
+    } else if let Some((text, target)) = inner.rsplit_once("->") {
+        (text, target)
+    } else if let Some((target, text)) = inner.split_once("<-") {
+        (text, target)
+    } else {
+        (inner, inner)
+    }
+}
+
+/// Remove the links from the text of a passage, returning the remaining text and the links.
+fn take_links(text: &str) -> (String, Vec<(&str, &str)>) {
+    let mut remaining = String::with_capacity(text.len());
+    let mut links = Vec::new();
+    let mut rest = text;
+
+    while let Some((before, after)) = rest.split_once("[[") {
+        let (inner, after) = match after.split_once("]]") {
+            Some(parts) => parts,
+            None => break,
+        };
+
+        remaining.push_str(before);
+        let (text, target) = link(inner);
+        links.push((text.trim(), target.trim()));
+        rest = after;
+    }
+
+    remaining.push_str(rest);
+    (remaining, links)
+}
+
+/// Check whether some text uses the macros or variables of a story format, such as SugarCube's
+/// `<<set $x to 1>>` or Harlowe's `(set: $x to 1)`.
+fn has_code(text: &str) -> bool {
+    let sugarcube = text.contains("<<") && text.contains(">>");
+
+    let harlowe = text.split('(').skip(1).any(|after| {
+        let name_length = after
+            .find(|c: char| !c.is_alphanumeric() && c != '-')
+            .unwrap_or(after.len());
+        name_length > 0 && after[name_length..].starts_with(':')
+    });
+
+    let variable = text
+        .split('$')
+        .skip(1)
+        .any(|after| after.starts_with(|c: char| c.is_alphabetic() || c == '_'));
+
+    sugarcube || harlowe || variable
+}
+
+/// Check whether a paragraph is a list of choices, starting and ending with a link, such as
+/// `[[Go down]] or [[stay here]].` Without its links, only the words joining them would be left.
+fn is_choices(paragraph: &str) -> bool {
+    let paragraph = paragraph.trim();
+    let end = paragraph.trim_end_matches(|c: char| c.is_ascii_punctuation() && c != ']');
+    paragraph.starts_with("[[") && end.ends_with("]]")
+}
+
+/// Check whether a line of text would be read as a directive or comment.
+fn is_plain_text(text: &str) -> bool {
+    let first_word = text.split_whitespace().next().unwrap_or_default();
+    DirectiveKind::from_str(first_word).is_none() && !text.starts_with('#')
+}
+
+/// Turn a passage name into a page identifier, such as `The Cellar` into `the-cellar`.
+fn identifier(name: &str) -> String {
+    let mut identifier = String::with_capacity(name.len());
+
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            identifier.push(c);
+        } else if !identifier.is_empty() && !identifier.ends_with('-') {
+            identifier.push('-');
+        }
+    }
+
+    match identifier.trim_end_matches('-') {
+        "" => "passage".to_owned(),
+        trimmed => trimmed.to_owned(),
+    }
+}
+
+/// Convert a Twee 3 story into a script, with a page for each passage titled by its name. The start
+/// passage becomes the first page. Anything which a script can't represent is left out and
+/// reported.
+pub fn import(input: &str) -> (String, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    let mut start = None;
+    let mut story = Vec::new();
+
+    for passage in passages(input) {
+        if passage.name == "StoryTitle" {
+            warnings.push(Warning::StoryTitle {
+                title: passage.text,
+            });
+        } else if passage.name == "StoryData" {
+            let data: serde_json::Value = serde_json::from_str(&passage.text).unwrap_or_default();
+            start = data["start"].as_str().map(str::to_owned);
+        } else if passage.tags.contains(&"script") || passage.tags.contains(&"stylesheet") {
+            warnings.push(Warning::DroppedPassage { name: passage.name });
+        } else {
+            story.push(passage);
+        }
+    }
+
+    // Twine starts at the passage named by the story data, or otherwise one named "Start".
+    let start = start.unwrap_or_else(|| "Start".to_owned());
+    if let Some(index) = story.iter().position(|passage| passage.name == start) {
+        let passage = story.remove(index);
+        story.insert(0, passage);
+    }
+
+    let mut identifiers = HashMap::new();
+    let mut used = HashSet::new();
+
+    for passage in &story {
+        let base = identifier(&passage.name);
+        let mut candidate = base.clone();
+        let mut count = 1;
+
+        while used.contains(&candidate) {
+            count += 1;
+            candidate = format!("{base}-{count}");
+        }
+
+        if count > 1 {
+            warnings.push(Warning::Renamed {
+                passage: passage.name.clone(),
+                identifier: candidate.clone(),
+            });
+        }
+
+        used.insert(candidate.clone());
+        identifiers.insert(passage.name.as_str(), candidate);
+    }
+
+    let mut output = String::new();
+
+    for passage in &story {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        let _ = write!(
+            output,
+            "page {}\n    title\n        {}\n",
+            identifiers[passage.name.as_str()],
+            passage.name
+        );
+
+        if let Some(template) = passage.tags.first() {
+            let _ = writeln!(output, "    template {template}");
+        }
+        if passage.tags.len() > 1 {
+            warnings.push(Warning::ExtraTags {
+                passage: passage.name.clone(),
+            });
+        }
+
+        if has_code(&passage.text) {
+            warnings.push(Warning::Code {
+                passage: passage.name.clone(),
+            });
+        }
+
+        let mut links = Vec::new();
+
+        let paragraphs: Vec<_> = passage
+            .text
+            .split("\n\n")
+            .filter_map(|paragraph| {
+                let (text, found) = take_links(paragraph);
+                links.extend(found);
+                (!is_choices(paragraph)).then_some(text)
+            })
+            .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|paragraph| !paragraph.is_empty())
+            .filter(|paragraph| {
+                let keep = is_plain_text(paragraph);
+                if !keep {
+                    warnings.push(Warning::DroppedParagraph {
+                        passage: passage.name.clone(),
+                        text: paragraph.clone(),
+                    });
+                }
+                keep
+            })
+            .collect();
+
+        if !paragraphs.is_empty() {
+            let _ = write!(
+                output,
+                "    text\n        {}\n",
+                paragraphs.join("\n\n        ")
+            );
+        }
+
+        for (text, target) in links {
+            match identifiers.get(target) {
+                Some(_) if !is_plain_text(text) => warnings.push(Warning::DroppedLink {
+                    page: passage.name.clone(),
+                    text: text.to_owned(),
+                }),
+                Some(identifier) => {
+                    let _ = write!(output, "    link {identifier}\n        {text}\n");
+                }
+                None => warnings.push(Warning::UnknownTarget {
+                    passage: passage.name.clone(),
+                    target: target.to_owned(),
+                }),
+            }
+        }
+    }
+
+    (output, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_code, identifier, import, is_choices, link, Warning};
+    use crate::script::Script;
+
+    #[test]
+    fn can_read_links() {
+        const EXAMPLES: &[(&str, (&str, &str))] = &[
+            ("Cellar", ("Cellar", "Cellar")),
+            ("Go down->Cellar", ("Go down", "Cellar")),
+            ("Go -> down->Cellar", ("Go -> down", "Cellar")),
+            ("Cellar<-Go down", ("Go down", "Cellar")),
+            ("Go down|Cellar", ("Go down", "Cellar")),
+        ];
+
+        for (inner, expected) in EXAMPLES {
+            assert_eq!(*expected, link(inner));
+        }
+    }
+
+    #[test]
+    fn can_make_identifiers() {
+        const EXAMPLES: &[(&str, &str)] = &[
+            ("Start", "start"),
+            ("The Dark  Cellar!", "the-dark-cellar"),
+            ("??", "passage"),
+            ("Café 2", "café-2"),
+        ];
+
+        for (name, expected) in EXAMPLES {
+            assert_eq!(*expected, identifier(name));
+        }
+    }
+
+    #[test]
+    fn can_detect_code() {
+        assert!(has_code("<<set $gold to 5>>"));
+        assert!(has_code("(if: $gold > 5)[Rich!]"));
+        assert!(has_code("You have $gold coins."));
+        assert!(!has_code("It costs $5 (or so: maybe more)."));
+    }
+
+    #[test]
+    fn can_detect_choices() {
+        assert!(is_choices("[[Go down]] or [[stay here]]."));
+        assert!(is_choices("[[Go down]]\n[[Stay here]]"));
+        assert!(!is_choices("You could [[go down]]."));
+        assert!(!is_choices("[[Go down]], if you dare."));
+    }
+
+    #[test]
+    fn can_import_story() {
+        let input = r#"Ignored text.
+
+:: StoryTitle
+The Cold
+
+:: StoryData
+{"ifid": "D674C58C-DEFA-4F70-B7A2-27742230C0FC", "start": "Wake Up"}
+
+:: Cellar [dark gloomy] {"position":"100,100"}
+It's dark down here.
+
+[[Go back up->Wake Up]]
+[[Nowhere]]
+
+:: Wake Up
+You wake up.
+It's cold.
+\:: Still cold.
+
+[[Go down|Cellar]] or [[stay here->Wake {Up}]].
+[[link the rope->Cellar]]
+
+link is not a paragraph.
+
+:: Wake \{Up\}
+You stay.
+
+:: Style [stylesheet]
+body { color: blue; }
+"#;
+
+        let (output, warnings) = import(input);
+
+        let expected = "page wake-up
+    title
+        Wake Up
+    text
+        You wake up. It's cold. :: Still cold.
+    link cellar
+        Go down
+    link wake-up-2
+        stay here
+
+page cellar
+    title
+        Cellar
+    template dark
+    text
+        It's dark down here.
+    link wake-up
+        Go back up
+
+page wake-up-2
+    title
+        Wake {Up}
+    text
+        You stay.
+";
+
+        assert_eq!(expected, output);
+        assert!(Script::new(&output, None).is_ok());
+
+        assert_eq!(
+            vec![
+                Warning::StoryTitle {
+                    title: "The Cold".to_owned()
+                },
+                Warning::DroppedPassage {
+                    name: "Style".to_owned()
+                },
+                Warning::Renamed {
+                    passage: "Wake {Up}".to_owned(),
+                    identifier: "wake-up-2".to_owned()
+                },
+                Warning::DroppedParagraph {
+                    passage: "Wake Up".to_owned(),
+                    text: "link is not a paragraph.".to_owned()
+                },
+                Warning::DroppedLink {
+                    page: "Wake Up".to_owned(),
+                    text: "link the rope".to_owned()
+                },
+                Warning::ExtraTags {
+                    passage: "Cellar".to_owned()
+                },
+                Warning::UnknownTarget {
+                    passage: "Cellar".to_owned(),
+                    target: "Nowhere".to_owned()
+                },
+            ],
+            warnings
+        );
+    }
+}
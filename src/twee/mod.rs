@@ -0,0 +1,151 @@
+//! Conversion between scripts and Twee 3, the text format of Twine stories.
+
+mod export;
+mod import;
+
+pub use export::export;
+pub use import::import;
+
+/// Something which could not be represented when converting to or from Twee. Conversion carries
+/// on without it.
+#[derive(Debug, PartialEq)]
+pub enum Warning {
+    /// Twee passages have a name, but no separate title.
+    DroppedTitle { page: String },
+
+    /// Twee has no equivalent to page metadata.
+    DroppedMeta { page: String, key: String },
+
+    /// Templates are exported as passage tags, which can't contain whitespace.
+    DroppedTemplate { page: String, template: String },
+
+    /// The text or target of a link would be misread as part of the link syntax.
+    DroppedLink { page: String, text: String },
+
+    /// Links which are hidden once taken, or unless a flag is set, need the macros of a story
+    /// format, so are written as plain links.
+    DroppedLinkModifier {
+        page: String,
+        text: String,
+        modifier: &'static str,
+    },
+
+    /// Conditional text and links need the macros of a story format, so are left out.
+    DroppedCondition { page: String, condition: String },
+
+    /// Random choices need the macros of a story format.
+    DroppedRandom { page: String, text: String },
+
+    /// The story title is set by the config, not the script.
+    StoryTitle { title: String },
+
+    /// Scripts and stylesheets of the story format have no place in a script.
+    DroppedPassage { name: String },
+
+    /// Macros and variables of the story format are kept as plain text.
+    Code { passage: String },
+
+    /// Only the first tag of a passage is used, as the name of its template.
+    ExtraTags { passage: String },
+
+    /// A link to a passage which doesn't exist.
+    UnknownTarget { passage: String, target: String },
+
+    /// A paragraph which would be read as a directive or comment.
+    DroppedParagraph { passage: String, text: String },
+
+    /// Passage names which sanitize to the same page identifier.
+    Renamed { passage: String, identifier: String },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::DroppedTitle { page } => write!(
+                f,
+                "Page {page:?} has a title, but passages are named by page identifier."
+            ),
+            Warning::DroppedMeta { page, key } => {
+                write!(f, "Page {page:?} has metadata {key:?}, which was dropped.")
+            }
+            Warning::DroppedTemplate { page, template } => write!(
+                f,
+                "Page {page:?} uses template {template:?}, which can't be written as a tag."
+            ),
+            Warning::DroppedLink { page, text } => {
+                write!(
+                    f,
+                    "Page {page:?} has link {text:?}, which can't be written."
+                )
+            }
+            Warning::DroppedLinkModifier {
+                page,
+                text,
+                modifier,
+            } => write!(
+                f,
+                "Page {page:?} has link {text:?}, which is always shown, since {modifier:?} needs \
+                story format macros."
+            ),
+            Warning::DroppedCondition { page, condition } => write!(
+                f,
+                "Page {page:?} has text or a link shown if {condition:?}, which needs story format \
+                macros, so was dropped."
+            ),
+            Warning::DroppedRandom { page, text } => write!(
+                f,
+                "Page {page:?} has random choice {text:?}, which needs story format macros, so was \
+                dropped."
+            ),
+            Warning::StoryTitle { title } => write!(
+                f,
+                "The story is titled {title:?}, which must be set as `title` in the `[story]` table \
+                of the config."
+            ),
+            Warning::DroppedPassage { name } => {
+                write!(
+                    f,
+                    "Passage {name:?} is a script or stylesheet, so was dropped."
+                )
+            }
+            Warning::Code { passage } => write!(
+                f,
+                "Passage {passage:?} uses macros or variables, which are kept as plain text."
+            ),
+            Warning::ExtraTags { passage } => write!(
+                f,
+                "Passage {passage:?} has several tags, only the first is used as its template."
+            ),
+            Warning::UnknownTarget { passage, target } => write!(
+                f,
+                "Passage {passage:?} links to {target:?}, which doesn't exist, so was dropped."
+            ),
+            Warning::DroppedParagraph { passage, text } => write!(
+                f,
+                "Passage {passage:?} has paragraph {text:?}, which would be read as a directive or \
+                comment, so was dropped."
+            ),
+            Warning::Renamed {
+                passage,
+                identifier,
+            } => write!(
+                f,
+                "Passage {passage:?} has the same identifier as another, so is now {identifier:?}."
+            ),
+        }
+    }
+}
+
+/// Escape the special characters of a passage name or tag.
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if matches!(c, '\\' | '[' | ']' | '{' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}